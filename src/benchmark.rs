@@ -0,0 +1,126 @@
+use super::{
+    register_components, spawner, AiState, FactionTable, GameLog, Identification, Map,
+    MapIndexingSystem, MonsterAI, MonsterAiState, Noises, Point, Position, RunState, SeenPlayer,
+    TileType, VisibilitySystem,
+};
+use bracket_lib::prelude::RandomNumberGenerator;
+use specs::prelude::*;
+use std::time::Instant;
+
+const BENCH_MONSTERS: usize = 200;
+const BENCH_TURNS: u32 = 100;
+
+// The first level with only the player on it; no window or context needed.
+fn bench_world() -> World {
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+
+    let map = Map::new_level(1);
+    let (player_x, player_y) = map.rooms[0].center();
+    let mut rng = RandomNumberGenerator::new();
+    ecs.insert(Identification::new(&mut rng));
+    ecs.insert(rng);
+    ecs.insert(spawner::SpawnRecord::default());
+    ecs.insert(Noises::new());
+    ecs.insert(FactionTable::load());
+    let player_entity = spawner::player(&mut ecs, player_x, player_y);
+    ecs.insert(map);
+    ecs.insert(Point::new(player_x, player_y));
+    ecs.insert(player_entity);
+    ecs.insert(GameLog::new(ggez::graphics::Font::default()));
+    ecs
+}
+
+// Fills the first level with kobolds in the given state and reports how long
+// a monster turn takes. Hunters chase the player and searchers all head for
+// the same room, so both share their path maps the way a real pack does.
+fn monster_turns(state: AiState) {
+    let mut ecs = bench_world();
+    let mut floor: Vec<(i32, i32)> = Vec::new();
+    let (width, height) = {
+        let map = ecs.fetch::<Map>();
+        let positions = ecs.read_storage::<Position>();
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let idx = map.xy_idx(x, y);
+                let taken = (&positions).join().any(|p| p.x == x && p.y == y);
+                if map.tiles[idx].tiletype == TileType::Floor && !taken {
+                    floor.push((x, y));
+                }
+            }
+        }
+        (map.width, map.height)
+    };
+
+    let mut rng = RandomNumberGenerator::new();
+    let mut spawned = 0;
+    while spawned < BENCH_MONSTERS && !floor.is_empty() {
+        let (x, y) = floor.remove((rng.roll_dice(1, floor.len() as i32) - 1) as usize);
+        spawner::kobold(&mut ecs, x, y);
+        spawned += 1;
+    }
+    let player = *ecs.fetch::<Point>();
+    let room = {
+        let map = ecs.fetch::<Map>();
+        let (x, y) = map.rooms[map.rooms.len() - 1].center();
+        Point::new(x, y)
+    };
+    for (ai, seen) in (
+        &mut ecs.write_storage::<MonsterAiState>(),
+        &mut ecs.write_storage::<SeenPlayer>(),
+    )
+        .join()
+    {
+        ai.state = state;
+        match state {
+            AiState::Hunting => seen.point = Some(player),
+            AiState::Searching => {
+                ai.destination = Some(room);
+                ai.search_turns = BENCH_TURNS as i32 + 1;
+            }
+            _ => {}
+        }
+    }
+    ecs.insert(RunState::MonsterTurn);
+
+    let mut mapindex = MapIndexingSystem {};
+    let mut vis = VisibilitySystem {};
+    let mut mob = MonsterAI {};
+    let start = Instant::now();
+    for _ in 0..BENCH_TURNS {
+        mapindex.run_now(&ecs);
+        vis.run_now(&ecs);
+        mob.run_now(&ecs);
+        ecs.maintain();
+    }
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+    println!(
+        "{} {:?} monsters on a {}x{} map: {:.3} ms per monster turn ({} turns)",
+        spawned,
+        state,
+        width,
+        height,
+        elapsed / BENCH_TURNS as f64,
+        BENCH_TURNS
+    );
+}
+
+// `cargo test --release benchmark -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn wandering_monster_turns() {
+    monster_turns(AiState::Wandering);
+}
+
+#[test]
+#[ignore]
+fn hunting_monster_turns() {
+    monster_turns(AiState::Hunting);
+}
+
+#[test]
+#[ignore]
+fn searching_monster_turns() {
+    monster_turns(AiState::Searching);
+}
@@ -0,0 +1,198 @@
+use super::{Enchantment, EquipmentSlot, Equipped};
+use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::fmt;
+
+// A d20 roll plus the attacker's power has to reach this plus the target's
+// defense to hit.
+pub const BASE_ARMOR_CLASS: i32 = 10;
+const ATTACK_DIE: i32 = 20;
+
+// Damage dice written as "NdS+B", e.g. "1d6+2", "2d4" or "1d8-1".
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Dice {
+    pub count: i32,
+    pub sides: i32,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub fn new(count: i32, sides: i32, bonus: i32) -> Dice {
+        Dice {
+            count,
+            sides,
+            bonus,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Dice> {
+        let text = text.trim();
+        let d = text.find('d')?;
+        let count = text[..d].parse::<i32>().ok()?;
+        let rest = &text[d + 1..];
+        let (sides, bonus) = match rest.find(['+', '-']) {
+            Some(sign) => (
+                rest[..sign].parse::<i32>().ok()?,
+                rest[sign..].trim_start_matches('+').parse::<i32>().ok()?,
+            ),
+            None => (rest.parse::<i32>().ok()?, 0),
+        };
+        if count < 1 || sides < 1 {
+            return None;
+        }
+        Some(Dice::new(count, sides, bonus))
+    }
+
+    // Never below zero, however bad the bonus.
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        i32::max(0, rng.roll_dice(self.count, self.sides) + self.bonus)
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.bonus {
+            0 => write!(f, "{}d{}", self.count, self.sides),
+            b if b > 0 => write!(f, "{}d{}+{}", self.count, self.sides, b),
+            b => write!(f, "{}d{}{}", self.count, self.sides, b),
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AttackRoll {
+    Fumble,
+    Miss,
+    Hit,
+    Critical,
+}
+
+// A natural 1 always misses and a natural 20 always hits critically,
+// whatever the numbers say.
+pub fn attack_roll(rng: &mut RandomNumberGenerator, power: i32, defense: i32) -> AttackRoll {
+    let natural = rng.roll_dice(1, ATTACK_DIE);
+    if natural == 1 {
+        AttackRoll::Fumble
+    } else if natural == ATTACK_DIE {
+        AttackRoll::Critical
+    } else if natural + power >= BASE_ARMOR_CLASS + defense {
+        AttackRoll::Hit
+    } else {
+        AttackRoll::Miss
+    }
+}
+
+// Critical hits roll the damage dice twice.
+pub fn damage_roll(rng: &mut RandomNumberGenerator, dice: &Dice, roll: AttackRoll) -> i32 {
+    match roll {
+        AttackRoll::Fumble | AttackRoll::Miss => 0,
+        AttackRoll::Hit => dice.roll(rng),
+        AttackRoll::Critical => dice.roll(rng) + dice.roll(rng),
+    }
+}
+
+// Enchantment of whatever `owner` wears in `slot`; 0 for plain gear or an
+// empty slot.
+pub fn slot_enchantment(
+    equipped: &ReadStorage<Equipped>,
+    enchantments: &ReadStorage<Enchantment>,
+    owner: Entity,
+    slot: EquipmentSlot,
+) -> i32 {
+    (equipped, enchantments)
+        .join()
+        .find(|(worn, _)| worn.owner == owner && worn.slot == slot)
+        .map_or(0, |(_, enchantment)| enchantment.level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLLS: i32 = 10000;
+
+    fn rolls(dice: &Dice) -> Vec<i32> {
+        let mut rng = RandomNumberGenerator::seeded(7);
+        (0..ROLLS).map(|_| dice.roll(&mut rng)).collect()
+    }
+
+    fn mean(rolls: &[i32]) -> f64 {
+        rolls.iter().sum::<i32>() as f64 / rolls.len() as f64
+    }
+
+    #[test]
+    fn parses_dice() {
+        assert_eq!(Dice::parse("1d6"), Some(Dice::new(1, 6, 0)));
+        assert_eq!(Dice::parse("2d4+1"), Some(Dice::new(2, 4, 1)));
+        assert_eq!(Dice::parse("1d8-1"), Some(Dice::new(1, 8, -1)));
+    }
+
+    #[test]
+    fn rejects_malformed_dice() {
+        for text in &["d6", "0d6", "1d0", "abc"] {
+            assert_eq!(Dice::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for text in &["1d6", "2d4+1", "1d8-1"] {
+            let dice = Dice::parse(text).unwrap();
+            assert_eq!(dice.to_string(), *text);
+            assert_eq!(Dice::parse(&dice.to_string()), Some(dice));
+        }
+    }
+
+    #[test]
+    fn rolls_stay_in_range() {
+        let rolls = rolls(&Dice::new(2, 4, 1));
+        assert_eq!(rolls.iter().min(), Some(&3));
+        assert_eq!(rolls.iter().max(), Some(&9));
+        assert!((mean(&rolls) - 6.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn negative_bonus_clamps_at_zero() {
+        // 1d4-3 is 0 three times out of four and 1 otherwise.
+        let rolls = rolls(&Dice::new(1, 4, -3));
+        assert_eq!(rolls.iter().min(), Some(&0));
+        assert_eq!(rolls.iter().max(), Some(&1));
+        assert!((mean(&rolls) - 0.25).abs() < 0.02);
+    }
+
+    #[test]
+    fn natural_one_always_fumbles() {
+        // With this much power every roll but a natural 1 hits.
+        let mut rng = RandomNumberGenerator::seeded(11);
+        let results: Vec<AttackRoll> = (0..ROLLS).map(|_| attack_roll(&mut rng, 100, 0)).collect();
+        assert!(results.contains(&AttackRoll::Fumble));
+        assert!(!results.contains(&AttackRoll::Miss));
+    }
+
+    #[test]
+    fn natural_twenty_always_crits() {
+        // With this much defense every roll but a natural 20 misses.
+        let mut rng = RandomNumberGenerator::seeded(13);
+        let results: Vec<AttackRoll> = (0..ROLLS).map(|_| attack_roll(&mut rng, 0, 100)).collect();
+        assert!(results.contains(&AttackRoll::Critical));
+        assert!(!results.contains(&AttackRoll::Hit));
+    }
+
+    #[test]
+    fn misses_deal_no_damage() {
+        let mut rng = RandomNumberGenerator::seeded(17);
+        let dice = Dice::new(2, 6, 3);
+        assert_eq!(damage_roll(&mut rng, &dice, AttackRoll::Miss), 0);
+        assert_eq!(damage_roll(&mut rng, &dice, AttackRoll::Fumble), 0);
+    }
+
+    #[test]
+    fn criticals_roll_the_dice_twice() {
+        // A one-sided die makes every roll the same: 1 + 2 on a hit.
+        let mut rng = RandomNumberGenerator::seeded(19);
+        let dice = Dice::new(1, 1, 2);
+        assert_eq!(damage_roll(&mut rng, &dice, AttackRoll::Hit), 3);
+        assert_eq!(damage_roll(&mut rng, &dice, AttackRoll::Critical), 6);
+    }
+}
@@ -1,4 +1,7 @@
-use super::{GameImage, Map, Point, Point2, PLAYER_WINDOW_HEIGHT, PLAYER_WINDOW_WIDTH, TILESIZE};
+use super::{
+    GameImage, Point, Point2, INVENTORY_PAGE_SIZE, PLAYER_WINDOW_HEIGHT, PLAYER_WINDOW_WIDTH,
+    TILESIZE,
+};
 use ggez::graphics::Color;
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
@@ -26,11 +29,11 @@ pub struct Position {
 
 impl Position {
     pub fn to_top(&self) -> i32 {
-        (-1 * (PLAYER_WINDOW_HEIGHT / 2)) + self.y
+        -(PLAYER_WINDOW_HEIGHT / 2) + self.y
     }
 
     pub fn to_left(&self) -> i32 {
-        (-1 * (PLAYER_WINDOW_WIDTH / 2)) + self.x
+        -(PLAYER_WINDOW_WIDTH / 2) + self.x
     }
 
     pub fn to_right(&self) -> i32 {
@@ -52,11 +55,11 @@ impl Position {
 }
 
 pub fn point_to_left(p: Point) -> i32 {
-    (-1 * (PLAYER_WINDOW_WIDTH / 2)) + p.x
+    -(PLAYER_WINDOW_WIDTH / 2) + p.x
 }
 
 pub fn point_to_top(p: Point) -> i32 {
-    (-1 * (PLAYER_WINDOW_HEIGHT / 2)) + p.y
+    -(PLAYER_WINDOW_HEIGHT / 2) + p.y
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -69,11 +72,136 @@ pub struct Viewshed {
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+// Trades from its backpack instead of fighting; bumping into it opens the shop.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Merchant {}
+
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SeenPlayer {
     pub point: Option<Point>,
 }
 
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiState {
+    Idle,
+    Sleeping,
+    Wandering,
+    Hunting,
+    Searching,
+    Fleeing,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct MonsterAiState {
+    pub state: AiState,
+    // Patrol room center while wandering, noise source while searching.
+    pub destination: Option<Point>,
+    pub search_turns: i32,
+}
+
+impl MonsterAiState {
+    pub fn new(state: AiState) -> MonsterAiState {
+        MonsterAiState {
+            state,
+            destination: None,
+            search_turns: 0,
+        }
+    }
+}
+
+// Names the table in spawner that is rolled when this monster dies.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct LootTable {
+    pub table: String,
+}
+
+// Monsters spawned together, e.g. a kobold pack, share an id.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Group {
+    pub id: i32,
+}
+
+// Percent chance that an unalerted monster fails to notice this creature.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Stealth {
+    pub value: i32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
+// Flee once HP drops below this percentage of max HP.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Morale {
+    pub flee_percent: i32,
+}
+
+// A monster attack or spell. `effect` is an entity carrying the same
+// Ranged/InflictsDamage/AreaOfEffect/Paralyze components a scroll would.
+#[derive(Debug, ConvertSaveload, Clone)]
+pub struct Ability {
+    pub effect: Entity,
+    pub range: i32,
+    pub cooldown: i32,
+    pub ready_in: i32,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Abilities {
+    pub abilities: Vec<Ability>,
+}
+
+// specs can't derive this for a Vec of entity-holding structs, so each
+// ability is converted on its own.
+impl<M: Marker + Serialize> ConvertSaveload<M> for Abilities
+where
+    for<'de> M: Deserialize<'de>,
+{
+    type Data = Vec<<Ability as ConvertSaveload<M>>::Data>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        self.abilities
+            .iter()
+            .map(|ability| ability.convert_into(&mut ids))
+            .collect()
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let abilities = data
+            .into_iter()
+            .map(|ability| Ability::convert_from(ability, &mut ids))
+            .collect::<Result<Vec<Ability>, NoError>>()?;
+        Ok(Abilities { abilities })
+    }
+}
+
+impl Abilities {
+    pub fn tick(&mut self) {
+        for ability in self.abilities.iter_mut() {
+            ability.ready_in = i32::max(0, ability.ready_in - 1);
+        }
+    }
+
+    // First ready ability that reaches `distance`; starts its cooldown.
+    pub fn use_ready(&mut self, distance: f32) -> Option<Entity> {
+        let ability = self
+            .abilities
+            .iter_mut()
+            .find(|a| a.ready_in == 0 && distance <= a.range as f32)?;
+        ability.ready_in = ability.cooldown;
+        Some(ability.effect)
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name: String,
@@ -90,6 +218,34 @@ pub struct CombatStats {
     pub power: i32,
 }
 
+// Melee damage as a dice string like "1d6+2", parsed by combat::Dice.
+// On a weapon it replaces its wielder's own dice.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DamageDice {
+    pub dice: String,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum HungerState {
+    WellFed,
+    Normal,
+    Hungry,
+    Starving,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HungerClock {
+    pub state: HungerState,
+    pub duration: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesFood {}
+
+// Reveals the lowest-lettered unidentified item in the reader's backpack.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Identifies {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct DurationTurnHeal {
     pub time: i32,
@@ -106,6 +262,48 @@ pub struct Item {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Consumable {}
 
+// A stack of identical items sharing one entity and one inventory letter.
+// Items with the same name merge when picked up; using one takes one off.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Quantity {
+    pub amount: i32,
+}
+
+// How heavy one of an item is; a stack weighs this times its quantity.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weight {
+    pub amount: i32,
+}
+
+// What a merchant asks for one of an item. They buy back at half.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Value {
+    pub price: i32,
+}
+
+// A pile of coins on the floor, taken by walking over it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+// The gold someone carries. It weighs nothing and takes no inventory letter.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Purse {
+    pub gold: i32,
+}
+
+// Used up one charge at a time instead of being deleted; does nothing at zero.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Charges {
+    pub current: i32,
+    pub max: i32,
+}
+
+// Refills the lowest-lettered drained item in the reader's backpack.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Recharges {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Potion {
     pub heal_amount: i32,
@@ -116,15 +314,51 @@ pub struct InBackpack {
     pub owner: Entity,
 }
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InventorySlot {
+    pub slot: i32,
+}
+
+impl InventorySlot {
+    pub fn letter(&self) -> char {
+        ((97 + self.slot % INVENTORY_PAGE_SIZE) as u8) as char
+    }
+
+    pub fn page(&self) -> i32 {
+        self.slot / INVENTORY_PAGE_SIZE
+    }
+}
+
 #[derive(Component, Debug, Clone, ConvertSaveload)]
 pub struct WantsToPickupItem {
     pub collected_by: Entity,
     pub item: Entity,
 }
 
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+    Magic,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    pub amount: Vec<(i32, DamageType)>,
+}
+
+// Damage of these types is halved.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Resistance {
+    pub kinds: Vec<DamageType>,
+}
+
+// Damage of these types is doubled.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vulnerability {
+    pub kinds: Vec<DamageType>,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -151,6 +385,7 @@ pub struct Ranged {
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
+    pub kind: DamageType,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -167,6 +402,7 @@ pub struct Paralyze {
 pub enum EquipmentSlot {
     Melee,
     Shield,
+    Ranged,
 }
 
 #[derive(Component, Clone, ConvertSaveload)]
@@ -174,19 +410,67 @@ pub struct Equippable {
     pub slot: EquipmentSlot,
 }
 
+// Added to the wearer's rolls with this item: to-hit and damage for weapons
+// and launchers, defense for shields. Shown in the name once known.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Enchantment {
+    pub level: i32,
+    pub known: bool,
+}
+
+// Will not come off, be dropped or be thrown once equipped.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Cursed {}
+
+// Lifts the curse from everything in the reader's backpack.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RemovesCurse {}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Equipped {
     pub owner: Entity,
     pub slot: EquipmentSlot,
 }
 
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AmmoKind {
+    Arrow,
+    Stone,
+}
+
+// A bow or sling. Its DamageDice are used for the shot, not the ammunition's.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Launcher {
+    pub ammo: AmmoKind,
+    pub range: i32,
+}
+
+// Shot by a launcher of the same kind. How many are in the stack is its Quantity.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ammunition {
+    pub kind: AmmoKind,
+}
+
+// Sends an item flying at the target; fired ammunition uses the equipped launcher.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToThrow {
+    pub item: Entity,
+    pub target: Point,
+    pub fired: bool,
+}
+
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        kind: DamageType,
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, kind));
         } else {
             let dmg = SufferDamage {
-                amount: vec![amount],
+                amount: vec![(amount, kind)],
             };
             store.insert(victim, dmg).expect("Unable to insert Damage");
         }
@@ -196,4 +480,6 @@ impl SufferDamage {
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub spawn_record: super::spawner::SpawnRecord,
+    pub identification: super::Identification,
 }
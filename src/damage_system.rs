@@ -1,4 +1,7 @@
-use super::{CombatStats, GameLog, Name, Player, SufferDamage};
+use super::{
+    gamelog::seen_by_player, spawner, Abilities, CombatStats, Enchantment, GameLog, Identification,
+    LogEvent, Map, Name, Noun, Player, Position, Resistance, SufferDamage, Vulnerability,
+};
 use bracket_lib::prelude::console;
 use specs::prelude::*;
 use std::cmp::max;
@@ -6,16 +9,66 @@ use std::cmp::max;
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Resistance>,
+        ReadStorage<'a, Vulnerability>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (
+            entities,
+            player_entity,
+            map,
+            mut log,
+            names,
+            positions,
+            resistances,
+            vulnerabilities,
+            mut stats,
+            mut damage,
+        ) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            let witnessed = entity == *player_entity || seen_by_player(&map, positions.get(entity));
+            for (amount, kind) in damage.amount.iter() {
+                let resisted = resistances
+                    .get(entity)
+                    .is_some_and(|r| r.kinds.contains(kind));
+                let vulnerable = vulnerabilities
+                    .get(entity)
+                    .is_some_and(|v| v.kinds.contains(kind));
+                let mut amount = *amount;
+                if resisted {
+                    amount /= 2;
+                }
+                if vulnerable {
+                    amount *= 2;
+                }
+                if witnessed && resisted != vulnerable {
+                    let target = Noun::of(&names, entity, *player_entity);
+                    log.push(if resisted {
+                        LogEvent::Resist {
+                            target,
+                            kind: *kind,
+                        }
+                    } else {
+                        LogEvent::Vulnerable {
+                            target,
+                            kind: *kind,
+                        }
+                    });
+                }
+                stats.hp -= amount;
+            }
             stats.hp = max(0, stats.hp);
         }
 
@@ -30,6 +83,9 @@ pub fn delete_the_dead(ecs: &mut World) {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let entities = ecs.entities();
         let names = ecs.read_storage::<Name>();
+        let abilities = ecs.read_storage::<Abilities>();
+        let map = ecs.fetch::<Map>();
+        let positions = ecs.read_storage::<Position>();
         let mut log = ecs.write_resource::<GameLog>();
 
         for (entity, stats) in (&entities, &combat_stats).join() {
@@ -37,11 +93,19 @@ pub fn delete_the_dead(ecs: &mut World) {
                 let player = players.get(entity);
                 match player {
                     None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
-                            log.entries.push(GameLog::dead_log(&victim_name.name))
+                        if seen_by_player(&map, positions.get(entity)) {
+                            if let Some(victim_name) = names.get(entity) {
+                                log.push(LogEvent::Dead {
+                                    victim: Noun::thing(&victim_name.name),
+                                })
+                            }
                         }
                         dead.push(entity);
+                        if let Some(abilities) = abilities.get(entity) {
+                            for ability in abilities.abilities.iter() {
+                                dead.push(ability.effect);
+                            }
+                        }
                     }
                     Some(_) => console::log("おまえはもう死んでいる"),
                 }
@@ -49,6 +113,28 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    for victim in dead.iter() {
+        let dropped = spawner::drop_loot(ecs, *victim);
+        let map = ecs.fetch::<Map>();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let player_entity = ecs.fetch::<Entity>();
+        let identification = ecs.fetch::<Identification>();
+        let enchantments = ecs.read_storage::<Enchantment>();
+        let mut log = ecs.write_resource::<GameLog>();
+        for item in dropped.iter() {
+            let seen = positions
+                .get(*item)
+                .is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+            if seen {
+                log.push(LogEvent::Drop {
+                    actor: Noun::of(&names, *victim, *player_entity),
+                    item: Noun::item(&names, &identification, &enchantments, *item),
+                });
+            }
+        }
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
@@ -0,0 +1,60 @@
+use super::{CombatStats, InBackpack, Quantity, Weight};
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::ops::Deref;
+
+// A fighter carries this much comfortably, plus a little for every point of
+// power. Past that the load spoils their aim; past half as much again they
+// can't lift anything more.
+const CARRY_BASE: i32 = 50;
+const CARRY_PER_POWER: i32 = 10;
+pub const BURDENED_PENALTY: i32 = 2;
+
+pub fn carry_capacity(stats: &CombatStats) -> i32 {
+    CARRY_BASE + stats.power * CARRY_PER_POWER
+}
+
+pub fn carry_limit(stats: &CombatStats) -> i32 {
+    carry_capacity(stats) * 3 / 2
+}
+
+// What an item on the floor or in a pack weighs, the whole stack at once.
+pub fn item_weight<Q>(
+    weights: &ReadStorage<Weight>,
+    quantities: &Storage<Quantity, Q>,
+    item: Entity,
+) -> i32
+where
+    Q: Deref<Target = MaskedStorage<Quantity>>,
+{
+    weights.get(item).map_or(0, |weight| {
+        weight.amount * quantities.get(item).map_or(1, |stack| stack.amount)
+    })
+}
+
+pub fn carried_weight<B, Q>(
+    entities: &Entities,
+    backpack: &Storage<InBackpack, B>,
+    weights: &ReadStorage<Weight>,
+    quantities: &Storage<Quantity, Q>,
+    owner: Entity,
+) -> i32
+where
+    B: Deref<Target = MaskedStorage<InBackpack>>,
+    Q: Deref<Target = MaskedStorage<Quantity>>,
+{
+    (entities, backpack)
+        .join()
+        .filter(|(_, pack)| pack.owner == owner)
+        .map(|(item, _)| item_weight(weights, quantities, item))
+        .sum()
+}
+
+// Taken off the attacker's power for every swing and shot while burdened.
+pub fn burden_penalty(stats: &CombatStats, carried: i32) -> i32 {
+    if carried > carry_capacity(stats) {
+        BURDENED_PENALTY
+    } else {
+        0
+    }
+}
@@ -0,0 +1,86 @@
+use bracket_lib::prelude::console;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const FACTIONS_FILE: &str = "./factions.json";
+pub const PLAYER_FACTION: &str = "Player";
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Reaction {
+    Attack,
+    Ignore,
+    Flee,
+}
+
+// One line of factions.json: {"faction": "Kobold", "toward": "Dragon", "reaction": "Flee"}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReactionEntry {
+    pub faction: String,
+    pub toward: String,
+    pub reaction: Reaction,
+}
+
+pub struct FactionTable {
+    reactions: HashMap<(String, String), Reaction>,
+}
+
+impl Default for FactionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FactionTable {
+    pub fn new() -> FactionTable {
+        let mut table = FactionTable {
+            reactions: HashMap::new(),
+        };
+        for monster in ["Kobold", "Dragon"].iter() {
+            table.set(PLAYER_FACTION, monster, Reaction::Attack);
+            table.set(monster, PLAYER_FACTION, Reaction::Attack);
+        }
+        table
+    }
+
+    // Loads the built-in table and applies factions.json on top of it.
+    pub fn load() -> FactionTable {
+        let mut table = FactionTable::new();
+        if !Path::new(FACTIONS_FILE).exists() {
+            return table;
+        }
+        let data = match fs::read_to_string(FACTIONS_FILE) {
+            Ok(data) => data,
+            Err(e) => {
+                console::log(format!("Cannot read {}: {}", FACTIONS_FILE, e));
+                return table;
+            }
+        };
+        match serde_json::from_str::<Vec<ReactionEntry>>(&data) {
+            Ok(entries) => {
+                for entry in entries.iter() {
+                    table.set(&entry.faction, &entry.toward, entry.reaction);
+                }
+            }
+            Err(e) => console::log(format!("Cannot parse {}: {}", FACTIONS_FILE, e)),
+        }
+        table
+    }
+
+    pub fn set(&mut self, faction: &str, toward: &str, reaction: Reaction) {
+        self.reactions
+            .insert((faction.to_string(), toward.to_string()), reaction);
+    }
+
+    // Unlisted pairs leave each other alone, and nobody turns on their own faction.
+    pub fn reaction(&self, faction: &str, toward: &str) -> Reaction {
+        if faction == toward {
+            return Reaction::Ignore;
+        }
+        *self
+            .reactions
+            .get(&(faction.to_string(), toward.to_string()))
+            .unwrap_or(&Reaction::Ignore)
+    }
+}
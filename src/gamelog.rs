@@ -1,77 +1,320 @@
-use ggez::graphics::{Color, TextFragment};
+use super::locale::{self, Locale};
+use super::{item_name, DamageType, Enchantment, HungerState, Identification, Map, Name, Position};
+use ggez::graphics::TextFragment;
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::ops::Deref;
 
-pub struct GameLog {
-    pub entries: Vec<Vec<TextFragment>>,
-    pub font: ggez::graphics::Font,
+pub const DEFAULT_LOG_HISTORY: usize = 500;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum LogCategory {
+    Combat,
+    Item,
+    Movement,
+    System,
 }
 
-const NAME_COLOR: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+// Someone or something named in a log line. The catalog needs to know
+// whether it is the player to pick "you" / "あなた" and verb forms.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Noun {
+    pub name: String,
+    pub is_player: bool,
+}
 
-impl GameLog {
-    pub fn dead_log(name: &String) -> Vec<TextFragment> {
-        vec![
-            TextFragment::new(name.to_string()).color(Color::new(1.0, 0.0, 0.0, 1.0)),
-            TextFragment::new("は死んだ。").color(Color::new(1.0, 0.0, 0.0, 1.0)),
-        ]
+impl Noun {
+    pub fn new<S: ToString>(name: S, is_player: bool) -> Noun {
+        Noun {
+            name: name.to_string(),
+            is_player,
+        }
+    }
+
+    pub fn thing<S: ToString>(name: S) -> Noun {
+        Noun::new(name, false)
+    }
+
+    pub fn of(names: &ReadStorage<Name>, entity: Entity, player_entity: Entity) -> Noun {
+        match names.get(entity) {
+            Some(name) => Noun::new(&name.name, entity == player_entity),
+            None => Noun::new("???", entity == player_entity),
+        }
     }
 
-    pub fn get_item_log(name: &String) -> Vec<TextFragment> {
-        vec![
-            TextFragment::new(name.to_string()).color(Color::new(1.0, 1.0, 0.0, 1.0)),
-            TextFragment::new("を拾った。").color(Color::new(1.0, 1.0, 1.0, 1.0)),
-        ]
+    // Items are named the way the player knows them.
+    pub fn item<D>(
+        names: &ReadStorage<Name>,
+        identification: &Identification,
+        enchantments: &Storage<Enchantment, D>,
+        entity: Entity,
+    ) -> Noun
+    where
+        D: Deref<Target = MaskedStorage<Enchantment>>,
+    {
+        Noun::thing(item_name(names, identification, enchantments, entity))
+    }
+}
+
+// Monsters' actions are only logged when the player can see them.
+pub fn seen_by_player(map: &Map, pos: Option<&Position>) -> bool {
+    match pos {
+        Some(pos) => map.visible_tiles[map.xy_idx(pos.x, pos.y)],
+        None => false,
     }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum LogEvent {
+    Welcome,
+    Dead {
+        victim: Noun,
+    },
+    PickUp {
+        actor: Noun,
+        item: Noun,
+    },
+    Drop {
+        actor: Noun,
+        item: Noun,
+    },
+    CannotDescend,
+    NothingToPickUp,
+    Heal {
+        user: Noun,
+        item: Noun,
+        amount: i32,
+    },
+    NoSuchItem,
+    Equip {
+        actor: Noun,
+        item: Noun,
+    },
+    Unequip {
+        actor: Noun,
+        item: Noun,
+    },
+    Eat {
+        user: Noun,
+        item: Noun,
+    },
+    Hunger {
+        state: HungerState,
+    },
+    NoLauncher,
+    NoAmmo,
+    Throw {
+        actor: Noun,
+        item: Noun,
+        fired: bool,
+    },
+    Break {
+        item: Noun,
+    },
+    Attack {
+        attacker: Noun,
+        target: Noun,
+        damage: i32,
+    },
+    Miss {
+        attacker: Noun,
+        target: Noun,
+    },
+    Fumble {
+        attacker: Noun,
+    },
+    Critical {
+        attacker: Noun,
+        target: Noun,
+        damage: i32,
+    },
+    Resist {
+        target: Noun,
+        kind: DamageType,
+    },
+    Vulnerable {
+        target: Noun,
+        kind: DamageType,
+    },
+    Paralyze {
+        target: Noun,
+    },
+    Descend,
+    Flee {
+        actor: Noun,
+    },
+    Rally {
+        actor: Noun,
+    },
+    Cast {
+        caster: Noun,
+        ability: Noun,
+    },
+    WakeUp {
+        actor: Noun,
+    },
+    UseItem {
+        user: Noun,
+        item: Noun,
+    },
+    Identify {
+        unknown: Noun,
+        item: Noun,
+    },
+    NothingToIdentify,
+    NoCharges {
+        item: Noun,
+    },
+    Recharge {
+        item: Noun,
+    },
+    NothingToRecharge,
+    Cursed {
+        item: Noun,
+    },
+    Uncurse {
+        item: Noun,
+    },
+    NothingToUncurse,
+    TooHeavy {
+        item: Noun,
+    },
+    Burdened,
+    PickUpGold {
+        amount: i32,
+    },
+    Buy {
+        item: Noun,
+        price: i32,
+    },
+    Sell {
+        item: Noun,
+        price: i32,
+    },
+    NotEnoughGold {
+        item: Noun,
+    },
+    SellEquipped {
+        item: Noun,
+    },
+}
 
-    pub fn drop_item_log(name: &String) -> Vec<TextFragment> {
-        vec![
-            TextFragment::new(name.to_string()).color(Color::new(1.0, 1.0, 0.0, 1.0)),
-            TextFragment::new("を落とした。").color(Color::new(1.0, 1.0, 1.0, 1.0)),
-        ]
+impl LogEvent {
+    pub fn category(&self) -> LogCategory {
+        match self {
+            LogEvent::Welcome => LogCategory::System,
+            LogEvent::Dead { .. } => LogCategory::Combat,
+            LogEvent::PickUp { .. } => LogCategory::Item,
+            LogEvent::Drop { .. } => LogCategory::Item,
+            LogEvent::CannotDescend => LogCategory::Movement,
+            LogEvent::NothingToPickUp => LogCategory::Item,
+            LogEvent::Heal { .. } => LogCategory::Item,
+            LogEvent::NoSuchItem => LogCategory::Item,
+            LogEvent::Identify { .. } => LogCategory::Item,
+            LogEvent::NothingToIdentify => LogCategory::Item,
+            LogEvent::NoCharges { .. } => LogCategory::Item,
+            LogEvent::Recharge { .. } => LogCategory::Item,
+            LogEvent::NothingToRecharge => LogCategory::Item,
+            LogEvent::Cursed { .. } => LogCategory::Item,
+            LogEvent::Uncurse { .. } => LogCategory::Item,
+            LogEvent::NothingToUncurse => LogCategory::Item,
+            LogEvent::TooHeavy { .. } => LogCategory::Item,
+            LogEvent::Burdened => LogCategory::System,
+            LogEvent::PickUpGold { .. } => LogCategory::Item,
+            LogEvent::Buy { .. } => LogCategory::Item,
+            LogEvent::Sell { .. } => LogCategory::Item,
+            LogEvent::NotEnoughGold { .. } => LogCategory::Item,
+            LogEvent::SellEquipped { .. } => LogCategory::Item,
+            LogEvent::Equip { .. } => LogCategory::Item,
+            LogEvent::Unequip { .. } => LogCategory::Item,
+            LogEvent::Eat { .. } => LogCategory::Item,
+            LogEvent::Hunger { .. } => LogCategory::System,
+            LogEvent::NoLauncher => LogCategory::Item,
+            LogEvent::NoAmmo => LogCategory::Item,
+            LogEvent::Throw { .. } => LogCategory::Combat,
+            LogEvent::Break { .. } => LogCategory::Item,
+            LogEvent::Attack { .. } => LogCategory::Combat,
+            LogEvent::Miss { .. } => LogCategory::Combat,
+            LogEvent::Fumble { .. } => LogCategory::Combat,
+            LogEvent::Critical { .. } => LogCategory::Combat,
+            LogEvent::Resist { .. } => LogCategory::Combat,
+            LogEvent::Vulnerable { .. } => LogCategory::Combat,
+            LogEvent::Paralyze { .. } => LogCategory::Combat,
+            LogEvent::Descend => LogCategory::Movement,
+            LogEvent::Flee { .. } => LogCategory::Combat,
+            LogEvent::Rally { .. } => LogCategory::Combat,
+            LogEvent::Cast { .. } => LogCategory::Combat,
+            LogEvent::WakeUp { .. } => LogCategory::Combat,
+            LogEvent::UseItem { .. } => LogCategory::Item,
+        }
     }
+}
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub event: LogEvent,
+    pub count: i32,
+}
 
-    pub fn cannot_down_log() -> Vec<TextFragment> {
-        vec![TextFragment::new("そこからは降りられない。").color(Color::new(0.5, 0.5, 0.5, 1.0))]
+impl LogEntry {
+    pub fn category(&self) -> LogCategory {
+        self.event.category()
     }
 
-    pub fn try_get_but_nothing_log() -> Vec<TextFragment> {
-        vec![TextFragment::new("そこには何もない。").color(Color::new(0.5, 0.5, 0.5, 1.0))]
+    pub fn text(&self, locale: Locale) -> String {
+        locale::render(&self.event, locale)
+            .iter()
+            .map(|f| f.text.clone())
+            .collect::<Vec<_>>()
+            .concat()
     }
 
-    pub fn heal_log(name: &String, amount: i32) -> Vec<TextFragment> {
-        vec![
-            TextFragment::new(name.to_string()).color(Color::new(1.0, 1.0, 0.0, 1.0)),
-            TextFragment::new("を使った。HPが").color(Color::new(1.0, 1.0, 1.0, 1.0)),
-            TextFragment::new(amount.to_string()).color(Color::new(1.0, 1.0, 0.0, 1.0)),
-            TextFragment::new("回復した。").color(Color::new(1.0, 1.0, 1.0, 1.0)),
-        ]
+    // Fragments plus the "x3" counter for collapsed messages.
+    pub fn display_fragments(&self, locale: Locale) -> Vec<TextFragment> {
+        let mut fragments = locale::render(&self.event, locale);
+        if self.count > 1 {
+            fragments
+                .push(TextFragment::new(format!(" x{}", self.count)).color(locale::SYSTEM_COLOR));
+        }
+        fragments
     }
+}
+
+pub struct GameLog {
+    pub entries: Vec<LogEntry>,
+    pub max_entries: usize,
+    pub font: ggez::graphics::Font,
+}
 
-    pub fn try_do_item_but_no_item() -> Vec<TextFragment> {
-        vec![TextFragment::new("そのようなアイテムを持っていない。")
-            .color(Color::new(0.5, 0.5, 0.5, 1.0))]
+impl GameLog {
+    pub fn new(font: ggez::graphics::Font) -> GameLog {
+        GameLog {
+            entries: Vec::new(),
+            max_entries: DEFAULT_LOG_HISTORY,
+            font,
+        }
     }
 
-    pub fn battle_log(name: &String, target_name: &String, damage: i32) -> Vec<TextFragment> {
-        vec![
-            TextFragment::new(name.to_string()).color(Color::new(1.0, 1.0, 0.0, 1.0)),
-            TextFragment::new("は"),
-            TextFragment::new(target_name.to_string()).color(Color::new(1.0, 1.0, 0.0, 1.0)),
-            TextFragment::new("に"),
-            TextFragment::new(damage.to_string()).color(Color::new(1.0, 0.0, 0.0, 1.0)),
-            TextFragment::new("のダメージを与えた。"),
-        ]
+    pub fn push(&mut self, event: LogEvent) {
+        if let Some(last) = self.entries.last_mut() {
+            if last.event == event {
+                last.count += 1;
+                return;
+            }
+        }
+        self.entries.push(LogEntry { event, count: 1 });
+        self.trim();
     }
 
-    pub fn paralyze_log(name: &String) -> Vec<TextFragment> {
-        vec![
-            TextFragment::new(name.to_string()).color(NAME_COLOR),
-            TextFragment::new("は"),
-            TextFragment::new("麻痺").color(Color::new(0.5, 0.5, 1.0, 1.0)),
-            TextFragment::new("した。"),
-        ]
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries;
+        self.trim();
     }
 
-    pub fn goto_next_level_log() -> Vec<TextFragment> {
-        vec![TextFragment::new("あなたは階段を下ることにした……。")]
+    fn trim(&mut self) {
+        if self.entries.len() > self.max_entries {
+            let overflow = self.entries.len() - self.max_entries;
+            self.entries.drain(0..overflow);
+        }
     }
 }
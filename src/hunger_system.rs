@@ -0,0 +1,53 @@
+use super::{DamageType, GameLog, HungerClock, HungerState, LogEvent, RunState, SufferDamage};
+use specs::prelude::*;
+
+pub struct HungerSystem {}
+// Turns spent in each state before sliding into the next one.
+pub const WELL_FED_TURNS: i32 = 200;
+const NORMAL_TURNS: i32 = 300;
+const HUNGRY_TURNS: i32 = 200;
+const STARVING_DAMAGE: i32 = 1;
+
+impl<'a> System<'a> for HungerSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, HungerClock>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, runstate, mut log, mut clocks, mut suffer_damage) = data;
+
+        // Once per turn, on the player's half of it.
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        for (entity, clock) in (&entities, &mut clocks).join() {
+            clock.duration -= 1;
+            if clock.duration < 1 {
+                let (state, duration) = match clock.state {
+                    HungerState::WellFed => (HungerState::Normal, NORMAL_TURNS),
+                    HungerState::Normal => (HungerState::Hungry, HUNGRY_TURNS),
+                    HungerState::Hungry | HungerState::Starving => (HungerState::Starving, 0),
+                };
+                if state != clock.state && entity == *player_entity {
+                    log.push(LogEvent::Hunger { state });
+                }
+                clock.state = state;
+                clock.duration = duration;
+            }
+            if clock.state == HungerState::Starving {
+                SufferDamage::new_damage(
+                    &mut suffer_damage,
+                    entity,
+                    STARVING_DAMAGE,
+                    DamageType::Physical,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::spawner::{UNIDENTIFIED_POTIONS, UNIDENTIFIED_SCROLLS};
+use super::{Enchantment, Name};
+use bracket_lib::prelude::RandomNumberGenerator;
+use ggez::graphics::{Color, TextFragment};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+
+const POTION_COLORS: [(&str, (f32, f32, f32)); 10] = [
+    ("Red", (1.0, 0.2, 0.2)),
+    ("Blue", (0.2, 0.4, 1.0)),
+    ("Green", (0.2, 0.9, 0.3)),
+    ("Murky", (0.45, 0.4, 0.25)),
+    ("Fizzy", (0.6, 1.0, 0.9)),
+    ("Golden", (1.0, 0.85, 0.2)),
+    ("Silver", (0.8, 0.8, 0.85)),
+    ("Smoky", (0.5, 0.5, 0.5)),
+    ("Milky", (1.0, 1.0, 0.95)),
+    ("Bubbling", (1.0, 0.5, 0.9)),
+];
+const POTION_GLYPHS: [&str; 4] = ["壺", "瓶", "薬", "液"];
+const SCROLL_GLYPHS: [&str; 8] = ["紙", "巻", "札", "符", "書", "簡", "帖", "冊"];
+const SCROLL_COLORS: [(f32, f32, f32); 4] = [
+    (1.0, 0.9, 0.7),
+    (0.9, 0.85, 0.75),
+    (1.0, 1.0, 0.9),
+    (0.85, 0.75, 0.6),
+];
+const SCROLL_SYLLABLES: [&str; 12] = [
+    "XY", "ZZY", "FOO", "BAR", "KLA", "TU", "NOR", "VEX", "QUA", "ELB", "RAS", "MOK",
+];
+
+// How one potion or scroll type is drawn this run: a glyph for text mode
+// and a tint for both modes.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Appearance {
+    pub glyph: String,
+    pub tint: (f32, f32, f32),
+}
+
+impl Appearance {
+    pub fn color(&self) -> Color {
+        Color::new(self.tint.0, self.tint.1, self.tint.2, 1.0)
+    }
+
+    pub fn text_fragment(&self) -> TextFragment {
+        TextFragment::new(self.glyph.as_str()).color(self.color())
+    }
+}
+
+// Which potion and scroll types the player knows, and what the others look
+// like until then. Rolled once per run and saved with the map.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Identification {
+    masks: HashMap<String, String>,
+    looks: HashMap<String, Appearance>,
+    known: HashSet<String>,
+}
+
+// Fisher-Yates, so that each type can take the next unused entry.
+fn shuffled<T: Copy>(rng: &mut RandomNumberGenerator, items: &[T]) -> Vec<T> {
+    let mut items = items.to_vec();
+    for i in (1..items.len()).rev() {
+        let j = rng.roll_dice(1, i as i32 + 1) as usize - 1;
+        items.swap(i, j);
+    }
+    items
+}
+
+impl Identification {
+    pub fn new(rng: &mut RandomNumberGenerator) -> Identification {
+        let mut masks = HashMap::new();
+        let mut looks = HashMap::new();
+        let mut used: HashSet<String> = HashSet::new();
+
+        let mut colors = shuffled(rng, &POTION_COLORS);
+        for name in UNIDENTIFIED_POTIONS.iter() {
+            let (color, tint) = colors.pop().expect("More potion types than colors");
+            let glyph = POTION_GLYPHS[rng.roll_dice(1, POTION_GLYPHS.len() as i32) as usize - 1];
+            masks.insert(name.to_string(), format!("{} Potion", color));
+            looks.insert(
+                name.to_string(),
+                Appearance {
+                    glyph: glyph.to_string(),
+                    tint,
+                },
+            );
+        }
+
+        let mut scroll_glyphs = shuffled(rng, &SCROLL_GLYPHS);
+        for name in UNIDENTIFIED_SCROLLS.iter() {
+            let glyph = scroll_glyphs.pop().expect("More scroll types than glyphs");
+            let tint = SCROLL_COLORS[rng.roll_dice(1, SCROLL_COLORS.len() as i32) as usize - 1];
+            looks.insert(
+                name.to_string(),
+                Appearance {
+                    glyph: glyph.to_string(),
+                    tint,
+                },
+            );
+
+            let mask = loop {
+                let mut label = String::new();
+                for _ in 0..rng.roll_dice(1, 2) + 1 {
+                    label += SCROLL_SYLLABLES
+                        [rng.roll_dice(1, SCROLL_SYLLABLES.len() as i32) as usize - 1];
+                }
+                let mask = format!("Scroll labelled {}", label);
+                if !used.contains(&mask) {
+                    break mask;
+                }
+            };
+            used.insert(mask.clone());
+            masks.insert(name.to_string(), mask);
+        }
+        Identification {
+            masks,
+            looks,
+            known: HashSet::new(),
+        }
+    }
+
+    pub fn is_known(&self, name: &str) -> bool {
+        !self.masks.contains_key(name) || self.known.contains(name)
+    }
+
+    // Returns true when this was news.
+    pub fn learn(&mut self, name: &str) -> bool {
+        if self.is_known(name) {
+            return false;
+        }
+        self.known.insert(name.to_string())
+    }
+
+    // How items of this type are drawn, or None for the ones that always
+    // look the same.
+    pub fn appearance(&self, name: &str) -> Option<&Appearance> {
+        self.looks.get(name)
+    }
+
+    // What the player sees an item called: the real name once its type is
+    // known, the run's made-up one before that.
+    pub fn apparent_name(&self, name: &str) -> String {
+        match self.masks.get(name) {
+            Some(mask) if !self.known.contains(name) => mask.to_string(),
+            _ => name.to_string(),
+        }
+    }
+}
+
+// An item's apparent name with its enchantment once that is known,
+// e.g. "Iron Sword +2".
+pub fn item_name<D>(
+    names: &ReadStorage<Name>,
+    identification: &Identification,
+    enchantments: &Storage<Enchantment, D>,
+    entity: Entity,
+) -> String
+where
+    D: Deref<Target = MaskedStorage<Enchantment>>,
+{
+    let name = match names.get(entity) {
+        Some(name) => identification.apparent_name(&name.name),
+        None => "???".to_string(),
+    };
+    match enchantments.get(entity) {
+        Some(enchantment) if enchantment.known => format!("{} {:+}", name, enchantment.level),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_unidentified_type_looks_different() {
+        for seed in 0..20 {
+            let identification = Identification::new(&mut RandomNumberGenerator::seeded(seed));
+            let potions: HashSet<String> = UNIDENTIFIED_POTIONS
+                .iter()
+                .map(|name| identification.apparent_name(name))
+                .collect();
+            assert_eq!(potions.len(), UNIDENTIFIED_POTIONS.len());
+            let scrolls: HashSet<String> = UNIDENTIFIED_SCROLLS
+                .iter()
+                .map(|name| identification.appearance(name).unwrap().glyph.clone())
+                .collect();
+            assert_eq!(scrolls.len(), UNIDENTIFIED_SCROLLS.len());
+        }
+    }
+}
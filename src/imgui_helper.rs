@@ -1,19 +1,25 @@
 use super::{
-    try_drop_item, try_use_item, CombatStats, InBackpack, MainMenuState, Name, Player, Renderable,
-    RunState, TILESIZE, WINDOWSIZE_HEIGHT, WINDOWSIZE_WIDTH,
+    ai_state_name, ammo_kind_name, buy_price, damage_type_name, drop_count, hunger_state_name,
+    inventory_page_count, item_name, item_weight, player_load, saveload_system, sell_price,
+    trade_page_count, try_buy, try_drop_item, try_sell, try_throw_item, try_use_item, ui_text,
+    AreaOfEffect, Charges, CombatStats, Consumable, Cursed, DamageDice, DamageType, Enchantment,
+    EquipmentSlot, Equippable, Equipped, GameLog, HungerClock, HungerState, Identification,
+    InBackpack, InflictsDamage, InventorySlot, Item, Launcher, Locale, LogCategory, MainMenuState,
+    Map, MonsterAiState, Name, Paralyze, Player, Point, Position, ProvidesHealing, Purse, Quantity,
+    Ranged, Resistance, RunState, TileType, UiText, Value, Vulnerability, Weight, TILESIZE,
+    WINDOWSIZE_HEIGHT, WINDOWSIZE_WIDTH,
 };
 
+use ggez::event::KeyCode;
 use ggez::graphics;
 use ggez::Context;
 
 use gfx_core::{handle::RenderTargetView, memory::Typed};
-use gfx_device_gl;
 
 use imgui::*;
 use imgui_gfx_renderer::*;
 
 use specs::prelude::*;
-use std::path::Path;
 use std::time::Instant;
 
 #[derive(Copy, Clone, PartialEq, Debug, Default)]
@@ -27,11 +33,17 @@ pub struct ImGuiWrapper {
     pub imgui: imgui::Context,
     pub has_save: bool,
     pub inventory_window_show: bool,
+    pub inventory_page: i32,
+    pub look_point: Option<Point>,
+    pub message_log_show: bool,
+    pub options_window_show: bool,
+    pub message_log_scroll: f32,
+    message_log_filter: ImString,
+    message_log_categories: [(LogCategory, bool); 4],
+    message_log_to_bottom: bool,
     pub renderer: Renderer<gfx_core::format::Rgba8, gfx_device_gl::Resources>,
     last_frame: Instant,
     mouse_state: MouseState,
-    show_popup: bool,
-    texture_id: Option<TextureId>,
 }
 
 impl ImGuiWrapper {
@@ -39,7 +51,7 @@ impl ImGuiWrapper {
         // Create the imgui object
         let mut imgui = imgui::Context::create();
         let (factory, gfx_device, _, _, _) = graphics::gfx_objects(ctx);
-        let unicode_font = imgui.fonts().add_font(&[FontSource::TtfData {
+        let _unicode_font = imgui.fonts().add_font(&[FontSource::TtfData {
             data: include_bytes!("../resources/unifont-13.ttf"),
             size_pixels: 16.0,
             config: Some(FontConfig {
@@ -67,19 +79,46 @@ impl ImGuiWrapper {
             }
         };
 
+        // Keys for text input
+        {
+            let io = imgui.io_mut();
+            io[Key::Tab] = KeyCode::Tab as _;
+            io[Key::LeftArrow] = KeyCode::Left as _;
+            io[Key::RightArrow] = KeyCode::Right as _;
+            io[Key::UpArrow] = KeyCode::Up as _;
+            io[Key::DownArrow] = KeyCode::Down as _;
+            io[Key::Home] = KeyCode::Home as _;
+            io[Key::End] = KeyCode::End as _;
+            io[Key::Delete] = KeyCode::Delete as _;
+            io[Key::Backspace] = KeyCode::Back as _;
+            io[Key::Enter] = KeyCode::Return as _;
+            io[Key::Escape] = KeyCode::Escape as _;
+        }
+
         // Renderer
         let renderer = Renderer::init(&mut imgui, &mut *factory, shaders).unwrap();
-        let has_save = Path::new("./savegame.json").exists();
+        let has_save = saveload_system::has_compatible_save();
         // Create instace
         Self {
             imgui,
             has_save,
             inventory_window_show: false,
+            inventory_page: 0,
+            look_point: None,
+            message_log_show: false,
+            options_window_show: false,
+            message_log_scroll: 0.0,
+            message_log_filter: ImString::with_capacity(64),
+            message_log_categories: [
+                (LogCategory::Combat, true),
+                (LogCategory::Item, true),
+                (LogCategory::Movement, true),
+                (LogCategory::System, true),
+            ],
+            message_log_to_bottom: true,
             renderer,
             last_frame: Instant::now(),
             mouse_state: MouseState::default(),
-            show_popup: false,
-            texture_id: None,
         }
     }
 
@@ -106,7 +145,7 @@ impl ImGuiWrapper {
         let ui = self.imgui.frame();
         let has_save;
         {
-            has_save = self.has_save.clone();
+            has_save = self.has_save;
         }
         let mut newrunstate;
         {
@@ -114,59 +153,271 @@ impl ImGuiWrapper {
         }
         let mut not_title = true;
         let mut push_any_inventory_button: Option<i32> = None;
+        let mut confirmed_drop: Option<(Entity, i32)> = None;
+        // (buying?, slot) of a trade button pressed this frame.
+        let mut pushed_trade_button: Option<(bool, i32)> = None;
+        let mut locale = *ecs.fetch::<Locale>();
         {
-            match newrunstate {
-                RunState::MainMenu { state: _ } => {
-                    // -------------------------------------
-                    // main window
-                    // -------------------------------------
-                    Window::new(im_str!("Start Menu"))
+            let options_window_show = &mut self.options_window_show;
+            if let RunState::MainMenu { state: _ } = newrunstate {
+                // -------------------------------------
+                // main window
+                // -------------------------------------
+                Window::new(im_str!("Start Menu"))
+                    .flags(
+                        WindowFlags::NO_TITLE_BAR | WindowFlags::NO_RESIZE | WindowFlags::NO_MOVE,
+                    )
+                    .size([300.0, 300.0], imgui::Condition::Always)
+                    .position([100.0, 100.0], imgui::Condition::Always)
+                    .build(&ui, || {
+                        ui.text(ui_text(UiText::Title, locale));
+                        ui.text(ui_text(UiText::Welcome, locale));
+                        ui.separator();
+                        if ui.small_button(&im_str!("{}###Start", ui_text(UiText::Start, locale))) {
+                            newrunstate = RunState::MainMenu {
+                                state: MainMenuState::NewGame,
+                            };
+                        }
+                        if has_save
+                            && ui.small_button(&im_str!(
+                                "{}###LoadGame",
+                                ui_text(UiText::LoadGame, locale)
+                            ))
+                            && saveload_system::has_compatible_save()
+                        {
+                            newrunstate = RunState::MainMenu {
+                                state: MainMenuState::LoadGame,
+                            };
+                        }
+                        if ui.small_button(&im_str!(
+                            "{}###Options",
+                            ui_text(UiText::Options, locale)
+                        )) {
+                            *options_window_show = true;
+                        }
+
+                        if ui.small_button(&im_str!("{}###Quit", ui_text(UiText::Quit, locale))) {
+                            newrunstate = RunState::MainMenu {
+                                state: MainMenuState::Quit,
+                            };
+                        }
+                    });
+                not_title = false;
+            }
+            if self.options_window_show {
+                // -----------------------------
+                // Options Window
+                // -----------------------------
+                let title = im_str!("{}###Options", ui_text(UiText::Options, locale));
+                let options_window_show = &mut self.options_window_show;
+                Window::new(&title)
+                    .size([240.0, 160.0], imgui::Condition::FirstUseEver)
+                    .position([420.0, 100.0], imgui::Condition::FirstUseEver)
+                    .flags(WindowFlags::NO_COLLAPSE)
+                    .build(&ui, || {
+                        ui.text(ui_text(UiText::Language, locale));
+                        for choice in Locale::ALL.iter() {
+                            ui.radio_button(&im_str!("{}", choice.label()), &mut locale, *choice);
+                        }
+                        ui.separator();
+                        if ui.small_button(&im_str!("{}###Close", ui_text(UiText::Close, locale))) {
+                            *options_window_show = false;
+                        }
+                    });
+            }
+            if not_title {
+                if self.inventory_window_show {
+                    // -----------------------------
+                    // Inventory Window
+                    // -----------------------------
+                    let player_entity = ecs.fetch::<Entity>();
+                    let entities = ecs.entities();
+                    let names = ecs.read_storage::<Name>();
+                    let backpack = ecs.read_storage::<InBackpack>();
+                    let slots = ecs.read_storage::<InventorySlot>();
+                    let equipped = ecs.read_storage::<Equipped>();
+                    let quantities = ecs.read_storage::<Quantity>();
+                    let charges = ecs.read_storage::<Charges>();
+                    let enchantments = ecs.read_storage::<Enchantment>();
+                    let cursed = ecs.read_storage::<Cursed>();
+                    let identification = ecs.fetch::<Identification>();
+                    let page_count = inventory_page_count(ecs);
+                    let (carried, capacity) = player_load(ecs);
+                    let inventory_page = &mut self.inventory_page;
+
+                    let mut items = (&entities, &backpack, &names, &slots)
+                        .join()
+                        .filter(|item| {
+                            item.1.owner == *player_entity && item.3.page() == *inventory_page
+                        })
+                        .map(|(entity, _pack, _name, slot)| {
+                            let mut label =
+                                item_name(&names, &identification, &enchantments, entity);
+                            if let Some(stack) = quantities.get(entity) {
+                                if stack.amount > 1 {
+                                    label = format!("{} x{}", label, stack.amount);
+                                }
+                            }
+                            if let Some(charge) = charges.get(entity) {
+                                label = format!("{} ({}/{})", label, charge.current, charge.max);
+                            }
+                            if equipped.get(entity).is_some() {
+                                label =
+                                    format!("{} ({})", label, ui_text(UiText::Equipped, locale));
+                            }
+                            let known_curse = cursed.get(entity).is_some()
+                                && enchantments.get(entity).is_some_and(|e| e.known);
+                            if known_curse {
+                                label = format!("{} ({})", label, ui_text(UiText::Cursed, locale));
+                            }
+                            (label, slot)
+                        })
+                        .collect::<Vec<_>>();
+                    items.sort_by_key(|item| item.1.slot);
+
+                    let title = im_str!(
+                        "{} [{}/{}] ({}/{})###Inventory",
+                        ui_text(UiText::Inventory, locale),
+                        carried,
+                        capacity,
+                        *inventory_page + 1,
+                        page_count
+                    );
+                    Window::new(&title)
+                        .size(
+                            [
+                                ImGuiWrapper::INVENTORY_WINDOW_WIDTH_SIZE,
+                                ImGuiWrapper::INVENTORY_WINDOW_HEIGHT_SIZE,
+                            ],
+                            imgui::Condition::Always,
+                        )
+                        .position([16.0, 16.0], imgui::Condition::Always)
+                        .opened(&mut self.inventory_window_show)
                         .flags(
-                            WindowFlags::NO_TITLE_BAR
+                            WindowFlags::NO_COLLAPSE
                                 | WindowFlags::NO_RESIZE
                                 | WindowFlags::NO_MOVE,
                         )
-                        .size([300.0, 300.0], imgui::Condition::Always)
-                        .position([100.0, 100.0], imgui::Condition::Always)
                         .build(&ui, || {
-                            ui.text(im_str!("Sushy -- Typical Roguelike!!"));
-                            ui.text(im_str!("ようこそ、Sushyeの世界へ！"));
-                            ui.separator();
-                            if ui.small_button(im_str!("Start")) {
-                                newrunstate = RunState::MainMenu {
-                                    state: MainMenuState::NewGame,
-                                };
+                            if page_count > 1 {
+                                if ui.small_button(im_str!("<")) && *inventory_page > 0 {
+                                    *inventory_page -= 1;
+                                }
+                                ui.same_line(0.0);
+                                if ui.small_button(im_str!(">")) && *inventory_page < page_count - 1
+                                {
+                                    *inventory_page += 1;
+                                }
                             }
-                            if has_save {
-                                if ui.small_button(im_str!("Load Game")) {
-                                    if Path::new("./savegame.json").exists() {
-                                        newrunstate = RunState::MainMenu {
-                                            state: MainMenuState::LoadGame,
-                                        };
-                                    }
+                            for (label, slot) in items.iter() {
+                                let button_text = im_str!("{}", slot.letter());
+                                if ui.small_button(&button_text) {
+                                    push_any_inventory_button = Some(slot.slot);
                                 }
+                                ui.same_line(0.0);
+                                ui.text(format!("- {}", label));
                             }
-
-                            if ui.small_button(im_str!("Quit")) {
-                                newrunstate = RunState::MainMenu {
-                                    state: MainMenuState::Quit,
-                                };
+                        });
+                }
+                if let RunState::ShowDropCount { item, count, max } = newrunstate {
+                    // -----------------------------
+                    // Drop Count Window
+                    // -----------------------------
+                    let names = ecs.read_storage::<Name>();
+                    let identification = ecs.fetch::<Identification>();
+                    let enchantments = ecs.read_storage::<Enchantment>();
+                    let label = item_name(&names, &identification, &enchantments, item);
+                    let mut chosen = count;
+                    let title = im_str!("{}###DropCount", ui_text(UiText::DropHowMany, locale));
+                    Window::new(&title)
+                        .position([16.0, 16.0], imgui::Condition::Always)
+                        .flags(
+                            WindowFlags::NO_COLLAPSE
+                                | WindowFlags::NO_MOVE
+                                | WindowFlags::ALWAYS_AUTO_RESIZE,
+                        )
+                        .build(&ui, || {
+                            ui.text(&label);
+                            if ui.small_button(im_str!("-")) && chosen > 1 {
+                                chosen -= 1;
+                            }
+                            ui.same_line(0.0);
+                            ui.text(format!("{} / {}", chosen, max));
+                            ui.same_line(0.0);
+                            if ui.small_button(im_str!("+")) && chosen < max {
+                                chosen += 1;
+                            }
+                            if ui.small_button(im_str!("OK")) {
+                                confirmed_drop = Some((item, chosen));
                             }
                         });
-                    not_title = false;
+                    newrunstate = RunState::ShowDropCount {
+                        item,
+                        count: chosen,
+                        max,
+                    };
                 }
-                _ => {}
-            }
-            if not_title {
-                if self.inventory_window_show {
+                if let RunState::ShowTrade { merchant } = newrunstate {
                     // -----------------------------
-                    // Inventory Window
+                    // Trade Window
                     // -----------------------------
                     let player_entity = ecs.fetch::<Entity>();
+                    let entities = ecs.entities();
                     let names = ecs.read_storage::<Name>();
                     let backpack = ecs.read_storage::<InBackpack>();
-                    let mut j = 0;
-                    Window::new(im_str!("Inventory"))
+                    let slots = ecs.read_storage::<InventorySlot>();
+                    let equipped = ecs.read_storage::<Equipped>();
+                    let quantities = ecs.read_storage::<Quantity>();
+                    let values = ecs.read_storage::<Value>();
+                    let enchantments = ecs.read_storage::<Enchantment>();
+                    let identification = ecs.fetch::<Identification>();
+                    let gold = ecs
+                        .read_storage::<Purse>()
+                        .get(*player_entity)
+                        .map_or(0, |purse| purse.gold);
+                    let page_count = trade_page_count(ecs, merchant);
+                    let trade_page = &mut self.inventory_page;
+                    *trade_page = i32::min(*trade_page, page_count - 1);
+                    let page = *trade_page;
+
+                    let price_list = |owner: Entity, buying: bool| {
+                        let mut rows = (&entities, &backpack, &slots)
+                            .join()
+                            .filter(|(item, pack, slot)| {
+                                pack.owner == owner
+                                    && slot.page() == page
+                                    && equipped.get(*item).is_none()
+                            })
+                            .filter_map(|(item, _, slot)| {
+                                let price = if buying {
+                                    buy_price(&values, &enchantments, item)
+                                } else {
+                                    sell_price(&values, &enchantments, item)
+                                }?;
+                                let mut label =
+                                    item_name(&names, &identification, &enchantments, item);
+                                if let Some(stack) = quantities.get(item) {
+                                    if stack.amount > 1 {
+                                        label = format!("{} x{}", label, stack.amount);
+                                    }
+                                }
+                                Some((label, slot, price))
+                            })
+                            .collect::<Vec<_>>();
+                        rows.sort_by_key(|row| row.1.slot);
+                        rows
+                    };
+                    let stock = price_list(merchant, true);
+                    let goods = price_list(*player_entity, false);
+
+                    let mut open = true;
+                    let title = im_str!(
+                        "{} ({}/{})###Trade",
+                        ui_text(UiText::Trade, locale),
+                        page + 1,
+                        page_count
+                    );
+                    Window::new(&title)
                         .size(
                             [
                                 ImGuiWrapper::INVENTORY_WINDOW_WIDTH_SIZE,
@@ -175,36 +426,190 @@ impl ImGuiWrapper {
                             imgui::Condition::Always,
                         )
                         .position([16.0, 16.0], imgui::Condition::Always)
-                        .opened(&mut self.inventory_window_show)
+                        .opened(&mut open)
                         .flags(
                             WindowFlags::NO_COLLAPSE
                                 | WindowFlags::NO_RESIZE
                                 | WindowFlags::NO_MOVE,
                         )
                         .build(&ui, || {
-                            for (_pack, name) in (&backpack, &names)
-                                .join()
-                                .filter(|item| item.0.owner == *player_entity)
-                            {
-                                let key_char = ((97 + j) as u8) as char;
-                                let button_text = im_str!("{}", key_char);
+                            if page_count > 1 {
+                                if ui.small_button(im_str!("<")) && *trade_page > 0 {
+                                    *trade_page -= 1;
+                                }
+                                ui.same_line(0.0);
+                                if ui.small_button(im_str!(">")) && *trade_page < page_count - 1 {
+                                    *trade_page += 1;
+                                }
+                            }
+                            ui.text(format!("{}: {}", ui_text(UiText::Gold, locale), gold));
+                            ui.separator();
+                            ui.text(ui_text(UiText::Buy, locale));
+                            for (label, slot, price) in stock.iter() {
+                                let button_text = im_str!("{}###Buy{}", slot.letter(), slot.slot);
                                 if ui.small_button(&button_text) {
-                                    push_any_inventory_button = Some(j);
+                                    pushed_trade_button = Some((true, slot.slot));
                                 }
                                 ui.same_line(0.0);
-                                ui.text(format!("- {}", name.name.to_string()));
-                                j += 1;
+                                ui.text(format!("- {} ({} G)", label, price));
+                            }
+                            ui.separator();
+                            ui.text(ui_text(UiText::Sell, locale));
+                            for (label, slot, price) in goods.iter() {
+                                let button_text = im_str!(
+                                    "{}###Sell{}",
+                                    slot.letter().to_ascii_uppercase(),
+                                    slot.slot
+                                );
+                                if ui.small_button(&button_text) {
+                                    pushed_trade_button = Some((false, slot.slot));
+                                }
+                                ui.same_line(0.0);
+                                ui.text(format!("- {} ({} G)", label, price));
                             }
                         });
+                    if !open {
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                }
+                if let Some(look_point) = self.look_point {
+                    // -----------------------------
+                    // Look Window
+                    // -----------------------------
+                    Window::new(&im_str!("{}###Look", ui_text(UiText::Look, locale)))
+                        .size(
+                            [
+                                ImGuiWrapper::INVENTORY_WINDOW_WIDTH_SIZE / 2.0,
+                                ImGuiWrapper::INVENTORY_WINDOW_HEIGHT_SIZE,
+                            ],
+                            imgui::Condition::FirstUseEver,
+                        )
+                        .position([16.0, 16.0], imgui::Condition::FirstUseEver)
+                        .flags(WindowFlags::NO_COLLAPSE)
+                        .build(&ui, || {
+                            ImGuiWrapper::build_look_window(&ui, ecs, look_point, locale);
+                        });
+                }
+                if self.message_log_show {
+                    // -----------------------------
+                    // Message Log Window
+                    // -----------------------------
+                    let gamelog = ecs.fetch::<GameLog>();
+                    let filter = &mut self.message_log_filter;
+                    let categories = &mut self.message_log_categories;
+                    let scroll = &mut self.message_log_scroll;
+                    let to_bottom = &mut self.message_log_to_bottom;
+                    let mut history = gamelog.max_entries as i32;
+                    Window::new(&im_str!(
+                        "{}###MessageLog",
+                        ui_text(UiText::MessageLog, locale)
+                    ))
+                    .size(
+                        [
+                            ImGuiWrapper::INVENTORY_WINDOW_WIDTH_SIZE,
+                            ImGuiWrapper::STATES_WINDOW_HEIGHT_SIZE - 64.0,
+                        ],
+                        imgui::Condition::FirstUseEver,
+                    )
+                    .position([16.0, 16.0], imgui::Condition::FirstUseEver)
+                    .opened(&mut self.message_log_show)
+                    .flags(WindowFlags::NO_COLLAPSE)
+                    .build(&ui, || {
+                        ui.input_text(
+                            &im_str!("{}###Search", ui_text(UiText::Search, locale)),
+                            filter,
+                        )
+                        .build();
+                        for (i, (category, show)) in categories.iter_mut().enumerate() {
+                            if i > 0 {
+                                ui.same_line(0.0);
+                            }
+                            let label = match category {
+                                LogCategory::Combat => {
+                                    im_str!("{}###Combat", ui_text(UiText::Combat, locale))
+                                }
+                                LogCategory::Item => {
+                                    im_str!("{}###Items", ui_text(UiText::Items, locale))
+                                }
+                                LogCategory::Movement => {
+                                    im_str!("{}###Movement", ui_text(UiText::Movement, locale))
+                                }
+                                LogCategory::System => {
+                                    im_str!("{}###System", ui_text(UiText::System, locale))
+                                }
+                            };
+                            ui.checkbox(&label, show);
+                        }
+                        ui.input_int(
+                            &im_str!("{}###History", ui_text(UiText::History, locale)),
+                            &mut history,
+                        )
+                        .build();
+                        ui.separator();
+
+                        let search = filter.to_str().to_lowercase();
+                        ChildWindow::new(im_str!("Message Log Entries"))
+                            .border(true)
+                            .build(&ui, || {
+                                for entry in gamelog.entries.iter().filter(|entry| {
+                                    categories.iter().any(|c| c.0 == entry.category() && c.1)
+                                        && entry.text(locale).to_lowercase().contains(&search)
+                                }) {
+                                    for (i, fragment) in
+                                        entry.display_fragments(locale).iter().enumerate()
+                                    {
+                                        if i > 0 {
+                                            ui.same_line_with_spacing(0.0, 0.0);
+                                        }
+                                        match fragment.color {
+                                            None => ui.text(&fragment.text),
+                                            Some(c) => ui
+                                                .text_colored([c.r, c.g, c.b, c.a], &fragment.text),
+                                        }
+                                    }
+                                }
+                                if *to_bottom {
+                                    ui.set_scroll_y(ui.scroll_max_y());
+                                    *to_bottom = false;
+                                } else if *scroll != 0.0 {
+                                    ui.set_scroll_y(ui.scroll_y() + *scroll);
+                                }
+                                *scroll = 0.0;
+                            });
+                    });
+                    let history = history.max(1) as usize;
+                    if history != gamelog.max_entries {
+                        drop(gamelog);
+                        ecs.fetch_mut::<GameLog>().set_max_entries(history);
+                    }
+                } else {
+                    self.message_log_to_bottom = true;
                 }
                 // ---------------------------------------
                 // Player States Window
                 // ---------------------------------------
                 let combat_stats = ecs.read_storage::<CombatStats>();
                 let players = ecs.read_storage::<Player>();
-                for (_player, stats) in (&players, &combat_stats).join() {
+                let equipped = ecs.read_storage::<Equipped>();
+                let names = ecs.read_storage::<Name>();
+                let clocks = ecs.read_storage::<HungerClock>();
+                let identification = ecs.fetch::<Identification>();
+                let enchantments = ecs.read_storage::<Enchantment>();
+                let (carried, capacity) = player_load(ecs);
+                let purses = ecs.read_storage::<Purse>();
+                for (player, _player, stats) in (&ecs.entities(), &players, &combat_stats).join() {
+                    let gold = purses.get(player).map_or(0, |purse| purse.gold);
+                    let worn = |slot: EquipmentSlot| {
+                        (&ecs.entities(), &equipped)
+                            .join()
+                            .find(|(_, e)| e.owner == player && e.slot == slot)
+                            .map_or(
+                                ui_text(UiText::Unequipped, locale).to_string(),
+                                |(item, _)| item_name(&names, &identification, &enchantments, item),
+                            )
+                    };
                     // Window
-                    Window::new(im_str!("Player"))
+                    Window::new(&im_str!("{}###Player", ui_text(UiText::Player, locale)))
                         .flags(WindowFlags::NO_COLLAPSE)
                         .size(
                             [
@@ -222,26 +627,66 @@ impl ImGuiWrapper {
                             imgui::Condition::FirstUseEver,
                         )
                         .build(&ui, || {
-                            ui.text(format!("HP: {} / {}", stats.hp, stats.max_hp));
+                            ui.text(format!(
+                                "{}: {} / {}",
+                                ui_text(UiText::Hp, locale),
+                                stats.hp,
+                                stats.max_hp
+                            ));
                             ProgressBar::new((stats.hp as f32) / (stats.max_hp as f32)).build(&ui);
+                            if let Some(clock) = clocks.get(player) {
+                                let color = match clock.state {
+                                    HungerState::WellFed => [0.0, 1.0, 0.0, 1.0],
+                                    HungerState::Normal => [1.0, 1.0, 1.0, 1.0],
+                                    HungerState::Hungry => [1.0, 0.6, 0.0, 1.0],
+                                    HungerState::Starving => [1.0, 0.0, 0.0, 1.0],
+                                };
+                                ui.text_colored(color, hunger_state_name(clock.state, locale));
+                            }
+                            ui.text(format!("{}: {}", ui_text(UiText::Gold, locale), gold));
+                            if carried > capacity {
+                                ui.text_colored(
+                                    [1.0, 0.6, 0.0, 1.0],
+                                    ui_text(UiText::Burdened, locale),
+                                );
+                            }
                             ui.spacing();
-                            if CollapsingHeader::new(&ui, im_str!("Equipment"))
-                                .open_on_arrow(true)
-                                .default_open(true)
-                                .build()
+                            if CollapsingHeader::new(
+                                &ui,
+                                &im_str!("{}###Equipment", ui_text(UiText::Equipment, locale)),
+                            )
+                            .open_on_arrow(true)
+                            .default_open(true)
+                            .build()
                             {
-                                ui.text("Weapon:");
+                                ui.text(format!("{}:", ui_text(UiText::Weapon, locale)));
+                                ui.same_line(0.0);
+                                ui.text_colored([0.0, 1.0, 1.0, 1.0], worn(EquipmentSlot::Melee));
+
+                                ui.text(format!("{}:", ui_text(UiText::Shield, locale)));
                                 ui.same_line(0.0);
-                                ui.text_colored([0.0, 1.0, 1.0, 1.0], "None");
+                                ui.text_colored([0.0, 1.0, 1.0, 1.0], worn(EquipmentSlot::Shield));
 
-                                ui.text("Shield:");
+                                ui.text(format!("{}:", ui_text(UiText::Ranged, locale)));
                                 ui.same_line(0.0);
-                                ui.text_colored([0.0, 1.0, 1.0, 1.0], "None");
+                                ui.text_colored([0.0, 1.0, 1.0, 1.0], worn(EquipmentSlot::Ranged));
                             };
                         });
                 }
             }
         }
+        if let Some((item, count)) = confirmed_drop {
+            newrunstate = drop_count(ecs, item, count);
+        }
+        if let (Some((buying, slot)), RunState::ShowTrade { merchant }) =
+            (pushed_trade_button, newrunstate)
+        {
+            newrunstate = if buying {
+                try_buy(ecs, merchant, slot)
+            } else {
+                try_sell(ecs, merchant, slot)
+            };
+        }
         match push_any_inventory_button {
             None => {}
             Some(j) => match newrunstate {
@@ -251,19 +696,31 @@ impl ImGuiWrapper {
                 RunState::ShowDropItem => {
                     newrunstate = try_drop_item(ecs, j, &mut self.inventory_window_show);
                 }
+                RunState::ShowThrowItem => {
+                    newrunstate = try_throw_item(ecs, j, &mut self.inventory_window_show);
+                }
                 _ => {}
             },
         }
         if !self.inventory_window_show
-            && (newrunstate == RunState::ShowDropItem || newrunstate == RunState::ShowInventory)
+            && (newrunstate == RunState::ShowDropItem
+                || newrunstate == RunState::ShowInventory
+                || newrunstate == RunState::ShowThrowItem)
         {
             newrunstate = RunState::AwaitingInput;
         }
+        if !self.message_log_show && newrunstate == RunState::ShowMessageLog {
+            newrunstate = RunState::AwaitingInput;
+        }
 
         {
             let mut runstate = ecs.fetch_mut::<RunState>();
             *runstate = newrunstate;
         }
+        {
+            let mut current_locale = ecs.fetch_mut::<Locale>();
+            *current_locale = locale;
+        }
 
         //
 
@@ -280,6 +737,252 @@ impl ImGuiWrapper {
             .unwrap();
     }
 
+    fn build_look_window(ui: &Ui, ecs: &World, look_point: Point, locale: Locale) {
+        let map = ecs.fetch::<Map>();
+        if look_point.x < 0
+            || look_point.y < 0
+            || look_point.x >= map.width
+            || look_point.y >= map.height
+        {
+            ui.text(ui_text(UiText::Nothing, locale));
+            return;
+        }
+        let idx = map.xy_idx(look_point.x, look_point.y);
+        if !map.revealed_tiles[idx] {
+            ui.text(ui_text(UiText::Unexplored, locale));
+            return;
+        }
+        match map.tiles[idx].tiletype {
+            TileType::Wall => ui.text(ui_text(UiText::Wall, locale)),
+            TileType::Floor => ui.text(ui_text(UiText::Floor, locale)),
+            TileType::DownStairs => ui.text(ui_text(UiText::DownStairs, locale)),
+        }
+        if !map.visible_tiles[idx] {
+            ui.text_colored([0.5, 0.5, 0.5, 1.0], ui_text(UiText::NotInSight, locale));
+            return;
+        }
+
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let paralyze = ecs.read_storage::<Paralyze>();
+        let ai_states = ecs.read_storage::<MonsterAiState>();
+        let items = ecs.read_storage::<Item>();
+        let equipped = ecs.read_storage::<Equipped>();
+        let equippable = ecs.read_storage::<Equippable>();
+        let ranged = ecs.read_storage::<Ranged>();
+        let inflicts_damage = ecs.read_storage::<InflictsDamage>();
+        let aoe = ecs.read_storage::<AreaOfEffect>();
+        let provides_healing = ecs.read_storage::<ProvidesHealing>();
+        let consumables = ecs.read_storage::<Consumable>();
+        let damage_dice = ecs.read_storage::<DamageDice>();
+        let launchers = ecs.read_storage::<Launcher>();
+        let quantities = ecs.read_storage::<Quantity>();
+        let charges = ecs.read_storage::<Charges>();
+        let resistances = ecs.read_storage::<Resistance>();
+        let vulnerabilities = ecs.read_storage::<Vulnerability>();
+        let identification = ecs.fetch::<Identification>();
+        let enchantments = ecs.read_storage::<Enchantment>();
+        let cursed = ecs.read_storage::<Cursed>();
+        let weights = ecs.read_storage::<Weight>();
+        let values = ecs.read_storage::<Value>();
+        let kind_names = |kinds: &Vec<DamageType>, locale: Locale| {
+            kinds
+                .iter()
+                .map(|kind| damage_type_name(*kind, locale))
+                .collect::<Vec<&str>>()
+                .join(", ")
+        };
+
+        for (entity, name, _pos) in (&entities, &names, &positions)
+            .join()
+            .filter(|e| e.2.x == look_point.x && e.2.y == look_point.y)
+        {
+            ui.separator();
+            ui.text_colored(
+                [1.0, 1.0, 0.0, 1.0],
+                item_name(&names, &identification, &enchantments, entity),
+            );
+
+            if let Some(stats) = combat_stats.get(entity) {
+                ui.text(format!(
+                    "{}: {} / {}",
+                    ui_text(UiText::Hp, locale),
+                    stats.hp,
+                    stats.max_hp
+                ));
+                ProgressBar::new((stats.hp as f32) / (stats.max_hp as f32)).build(ui);
+                ui.text(format!(
+                    "{}: {}  {}: {}",
+                    ui_text(UiText::Power, locale),
+                    stats.power,
+                    ui_text(UiText::Defense, locale),
+                    stats.defense
+                ));
+                if let Some(dice) = damage_dice.get(entity) {
+                    ui.text(format!(
+                        "{}: {}",
+                        ui_text(UiText::Damage, locale),
+                        dice.dice
+                    ));
+                }
+                if let Some(resistance) = resistances.get(entity) {
+                    ui.text(format!(
+                        "{}: {}",
+                        ui_text(UiText::Resist, locale),
+                        kind_names(&resistance.kinds, locale)
+                    ));
+                }
+                if let Some(vulnerability) = vulnerabilities.get(entity) {
+                    ui.text(format!(
+                        "{}: {}",
+                        ui_text(UiText::Weak, locale),
+                        kind_names(&vulnerability.kinds, locale)
+                    ));
+                }
+            }
+
+            if items.get(entity).is_none() {
+                if let Some(ai) = ai_states.get(entity) {
+                    ui.text(format!(
+                        "{}: {}",
+                        ui_text(UiText::State, locale),
+                        ai_state_name(ai.state, locale)
+                    ));
+                }
+                if let Some(paralyze) = paralyze.get(entity) {
+                    ui.text_colored(
+                        [0.5, 0.5, 1.0, 1.0],
+                        format!(
+                            "{}: {} {}",
+                            ui_text(UiText::Paralyzed, locale),
+                            paralyze.turns,
+                            ui_text(UiText::Turns, locale)
+                        ),
+                    );
+                }
+                for (equipment_entity, equipment) in (&entities, &equipped)
+                    .join()
+                    .filter(|e| e.1.owner == entity)
+                {
+                    let slot = match equipment.slot {
+                        EquipmentSlot::Melee => ui_text(UiText::Weapon, locale),
+                        EquipmentSlot::Shield => ui_text(UiText::Shield, locale),
+                        EquipmentSlot::Ranged => ui_text(UiText::Ranged, locale),
+                    };
+                    ui.text(format!("{}:", slot));
+                    ui.same_line(0.0);
+                    ui.text_colored(
+                        [0.0, 1.0, 1.0, 1.0],
+                        item_name(&names, &identification, &enchantments, equipment_entity),
+                    );
+                }
+                continue;
+            }
+
+            if weights.get(entity).is_some() {
+                ui.text(format!(
+                    "{}: {}",
+                    ui_text(UiText::Weight, locale),
+                    item_weight(&weights, &quantities, entity)
+                ));
+            }
+            if let Some(price) = buy_price(&values, &enchantments, entity) {
+                ui.text(format!("{}: {} G", ui_text(UiText::Value, locale), price));
+            }
+            // What an unidentified item does is the whole mystery.
+            if !identification.is_known(&name.name) {
+                ui.text_colored([0.5, 0.5, 0.5, 1.0], ui_text(UiText::Unidentified, locale));
+                continue;
+            }
+            if let Some(enchantment) = enchantments.get(entity) {
+                if enchantment.known && cursed.get(entity).is_some() {
+                    ui.text_colored([1.0, 0.2, 0.2, 1.0], ui_text(UiText::Cursed, locale));
+                }
+            }
+            if let Some(equippable) = equippable.get(entity) {
+                let slot = match equippable.slot {
+                    EquipmentSlot::Melee => ui_text(UiText::Weapon, locale),
+                    EquipmentSlot::Shield => ui_text(UiText::Shield, locale),
+                    EquipmentSlot::Ranged => ui_text(UiText::Ranged, locale),
+                };
+                ui.text(format!("{}: {}", ui_text(UiText::Equip, locale), slot));
+            }
+            if let Some(launcher) = launchers.get(entity) {
+                ui.text(format!(
+                    "{}: {}  {}: {}",
+                    ui_text(UiText::Range, locale),
+                    launcher.range,
+                    ui_text(UiText::Ammo, locale),
+                    ammo_kind_name(launcher.ammo, locale)
+                ));
+            }
+            if let Some(stack) = quantities.get(entity) {
+                ui.text(format!(
+                    "{}: {}",
+                    ui_text(UiText::Count, locale),
+                    stack.amount
+                ));
+            }
+            if let Some(charge) = charges.get(entity) {
+                ui.text(format!(
+                    "{}: {} / {}",
+                    ui_text(UiText::Charges, locale),
+                    charge.current,
+                    charge.max
+                ));
+            }
+            if let Some(dice) = damage_dice.get(entity) {
+                ui.text(format!(
+                    "{}: {}",
+                    ui_text(UiText::Damage, locale),
+                    dice.dice
+                ));
+            }
+            if let Some(healing) = provides_healing.get(entity) {
+                ui.text(format!(
+                    "{}: {}",
+                    ui_text(UiText::Heal, locale),
+                    healing.heal_amount
+                ));
+            }
+            if let Some(damage) = inflicts_damage.get(entity) {
+                ui.text(format!(
+                    "{}: {} ({})",
+                    ui_text(UiText::Damage, locale),
+                    damage.damage,
+                    damage_type_name(damage.kind, locale)
+                ));
+            }
+            if let Some(ranged) = ranged.get(entity) {
+                ui.text(format!(
+                    "{}: {}",
+                    ui_text(UiText::Range, locale),
+                    ranged.range
+                ));
+            }
+            if let Some(aoe) = aoe.get(entity) {
+                ui.text(format!(
+                    "{}: {}",
+                    ui_text(UiText::Radius, locale),
+                    aoe.radius
+                ));
+            }
+            if let Some(paralyze) = paralyze.get(entity) {
+                ui.text(format!(
+                    "{}: {} {}",
+                    ui_text(UiText::Paralyze, locale),
+                    paralyze.turns,
+                    ui_text(UiText::Turns, locale)
+                ));
+            }
+            if consumables.get(entity).is_some() {
+                ui.text_colored([0.5, 0.5, 0.5, 1.0], ui_text(UiText::Consumable, locale));
+            }
+        }
+    }
+
     fn update_mouse(&mut self) {
         self.imgui.io_mut().mouse_pos =
             [self.mouse_state.pos.0 as f32, self.mouse_state.pos.1 as f32];
@@ -303,4 +1006,21 @@ impl ImGuiWrapper {
     pub fn update_mouse_down(&mut self, pressed: (bool, bool, bool)) {
         self.mouse_state.pressed = pressed;
     }
+
+    pub fn update_mouse_wheel(&mut self, wheel: f32) {
+        self.mouse_state.wheel += wheel;
+    }
+
+    pub fn update_key(&mut self, keycode: KeyCode, pressed: bool) {
+        self.imgui.io_mut().keys_down[keycode as usize] = pressed;
+    }
+
+    pub fn update_text(&mut self, character: char) {
+        self.imgui.io_mut().add_input_character(character);
+    }
+
+    // True while a text box has focus, so keys should not reach the game.
+    pub fn want_capture_keyboard(&self) -> bool {
+        self.imgui.io().want_capture_keyboard
+    }
 }
@@ -1,10 +1,16 @@
 use super::{
-    gamelog::GameLog, AreaOfEffect, CombatStats, Consumable, DropItem, InBackpack, InflictsDamage,
-    Map, Name, Paralyze, Position, ProvidesHealing, SufferDamage, WantsToPickupItem,
-    WantsToUseItem,
+    carried_weight, carry_capacity, carry_limit, gamelog::seen_by_player, gamelog::GameLog,
+    gamelog::LogEvent, gamelog::Noun, item_weight, AreaOfEffect, Charges, CombatStats, Consumable,
+    Cursed, DropItem, Enchantment, Equippable, Equipped, HungerClock, HungerState, Identification,
+    Identifies, InBackpack, InflictsDamage, InventorySlot, Item, Map, Name, Noises, Paralyze,
+    Position, ProvidesFood, ProvidesHealing, Quantity, Recharges, RemovesCurse, SufferDamage,
+    WantsToPickupItem, WantsToUseItem, Weight, WELL_FED_TURNS,
 };
 use specs::prelude::*;
 
+// An explosion is heard this many tiles beyond its blast radius.
+const BLAST_NOISE_RADIUS: i32 = 8;
+
 pub struct ItemCollectionSystem {}
 
 impl<'a> System<'a> for ItemCollectionSystem {
@@ -16,13 +22,110 @@ impl<'a> System<'a> for ItemCollectionSystem {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, InventorySlot>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, Quantity>,
+        ReadExpect<'a, Identification>,
+        ReadStorage<'a, Enchantment>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Weight>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            mut slots,
+            map,
+            entities,
+            mut quantities,
+            identification,
+            enchantments,
+            combat_stats,
+            weights,
+        ) = data;
 
         for pickup in wants_pickup.join() {
+            let witnessed = pickup.collected_by == *player_entity
+                || seen_by_player(&map, positions.get(pickup.collected_by));
+
+            // Nothing past the hard limit can be lifted; the item stays put.
+            let carried = carried_weight(
+                &entities,
+                &backpack,
+                &weights,
+                &quantities,
+                pickup.collected_by,
+            );
+            let load = carried + item_weight(&weights, &quantities, pickup.item);
+            let mut burdened = false;
+            if let Some(stats) = combat_stats.get(pickup.collected_by) {
+                if load > carry_limit(stats) {
+                    if pickup.collected_by == *player_entity {
+                        gamelog.push(LogEvent::TooHeavy {
+                            item: Noun::item(&names, &identification, &enchantments, pickup.item),
+                        });
+                    }
+                    continue;
+                }
+                burdened = pickup.collected_by == *player_entity
+                    && carried <= carry_capacity(stats)
+                    && load > carry_capacity(stats);
+            }
+
+            // A stackable item joins a stack of the same name already in the backpack.
+            let picked_name = names.get(pickup.item).map(|name| name.name.to_string());
+            let stack = match (quantities.get(pickup.item), picked_name) {
+                (Some(_), Some(picked_name)) => (&entities, &backpack, &quantities, &names)
+                    .join()
+                    .find(|(entity, pack, _, name)| {
+                        *entity != pickup.item
+                            && pack.owner == pickup.collected_by
+                            && name.name == picked_name
+                    })
+                    .map(|(entity, _, _, _)| entity),
+                _ => None,
+            };
+            if let Some(stack) = stack {
+                let amount = quantities
+                    .get(pickup.item)
+                    .map_or(0, |picked| picked.amount);
+                if let Some(stack) = quantities.get_mut(stack) {
+                    stack.amount += amount;
+                }
+                if witnessed {
+                    gamelog.push(LogEvent::PickUp {
+                        actor: Noun::of(&names, pickup.collected_by, *player_entity),
+                        item: Noun::item(&names, &identification, &enchantments, pickup.item),
+                    });
+                }
+                if burdened {
+                    gamelog.push(LogEvent::Burdened);
+                }
+                entities.delete(pickup.item).expect("Delete failed");
+                continue;
+            }
+
+            // The letter stays with the item until it leaves the backpack,
+            // so take the lowest one nobody in this backpack is using.
+            let used_slots: Vec<i32> = (&backpack, &slots)
+                .join()
+                .filter(|item| item.0.owner == pickup.collected_by)
+                .map(|item| item.1.slot)
+                .collect();
+            let mut slot = 0;
+            while used_slots.contains(&slot) {
+                slot += 1;
+            }
+            slots
+                .insert(pickup.item, InventorySlot { slot })
+                .expect("Unable to insert inventory slot");
+
             positions.remove(pickup.item);
             backpack
                 .insert(
@@ -33,9 +136,14 @@ impl<'a> System<'a> for ItemCollectionSystem {
                 )
                 .expect("Unable to insert backpack entry");
 
-            if pickup.collected_by == *player_entity {
-                let item_name = &names.get(pickup.item).unwrap().name;
-                gamelog.entries.push(GameLog::get_item_log(&item_name));
+            if witnessed {
+                gamelog.push(LogEvent::PickUp {
+                    actor: Noun::of(&names, pickup.collected_by, *player_entity),
+                    item: Noun::item(&names, &identification, &enchantments, pickup.item),
+                });
+            }
+            if burdened {
+                gamelog.push(LogEvent::Burdened);
             }
         }
 
@@ -60,6 +168,24 @@ impl<'a> System<'a> for ItemUseSystem {
         WriteStorage<'a, SufferDamage>,
         WriteStorage<'a, Paralyze>,
         ReadExpect<'a, Map>,
+        WriteExpect<'a, Noises>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Item>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        ReadStorage<'a, ProvidesFood>,
+        WriteStorage<'a, HungerClock>,
+        WriteExpect<'a, Identification>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, InventorySlot>,
+        // Grouped to stay under the 26-element SystemData limit.
+        (WriteStorage<'a, Charges>, WriteStorage<'a, Quantity>),
+        (WriteStorage<'a, Enchantment>, WriteStorage<'a, Cursed>),
+        (
+            ReadStorage<'a, Identifies>,
+            ReadStorage<'a, Recharges>,
+            ReadStorage<'a, RemovesCurse>,
+        ),
     );
     fn run(&mut self, data: Self::SystemData) {
         let (
@@ -76,21 +202,124 @@ impl<'a> System<'a> for ItemUseSystem {
             mut suffer_damage,
             mut paralyze,
             map,
+            mut noises,
+            positions,
+            items,
+            equippable,
+            mut equipped,
+            foods,
+            mut clocks,
+            mut identification,
+            backpack,
+            slots,
+            (mut charges, mut quantities),
+            (mut enchantments, mut cursed),
+            (identifies, recharges, removes_curse),
         ) = data;
 
         for (entity, use_item) in (&entities, &wants_use).join() {
-            let consumable = consumables.get(use_item.item);
-            match consumable {
-                None => {}
-                Some(_) => {
-                    entities.delete(use_item.item).expect("Delefe failed");
+            let witnessed = entity == *player_entity || seen_by_player(&map, positions.get(entity));
+
+            // Using equipment puts it on, replacing whatever is in that slot,
+            // or takes it off again if it is already worn.
+            if let Some(can_equip) = equippable.get(use_item.item) {
+                let mut taken_off: Vec<Entity> = (&entities, &equipped)
+                    .join()
+                    .filter(|(_, worn)| worn.owner == entity && worn.slot == can_equip.slot)
+                    .map(|(item, _)| item)
+                    .collect();
+                let already_worn = taken_off.contains(&use_item.item);
+                // Nothing comes off while a cursed item holds the slot.
+                let stuck = taken_off.iter().find(|item| cursed.get(**item).is_some());
+                if let Some(stuck) = stuck {
+                    if witnessed {
+                        gamelog.push(LogEvent::Cursed {
+                            item: Noun::item(&names, &identification, &enchantments, *stuck),
+                        });
+                    }
+                    continue;
+                }
+                for item in taken_off.drain(..) {
+                    equipped.remove(item);
+                    if witnessed {
+                        gamelog.push(LogEvent::Unequip {
+                            actor: Noun::of(&names, entity, *player_entity),
+                            item: Noun::item(&names, &identification, &enchantments, item),
+                        });
+                    }
+                }
+                if !already_worn {
+                    equipped
+                        .insert(
+                            use_item.item,
+                            Equipped {
+                                owner: entity,
+                                slot: can_equip.slot,
+                            },
+                        )
+                        .expect("Unable to equip");
+                    // Wearing something shows how good it really is.
+                    if let Some(enchantment) = enchantments.get_mut(use_item.item) {
+                        enchantment.known = true;
+                    }
+                    if witnessed {
+                        gamelog.push(LogEvent::Equip {
+                            actor: Noun::of(&names, entity, *player_entity),
+                            item: Noun::item(&names, &identification, &enchantments, use_item.item),
+                        });
+                        if cursed.get(use_item.item).is_some() {
+                            gamelog.push(LogEvent::Cursed {
+                                item: Noun::item(
+                                    &names,
+                                    &identification,
+                                    &enchantments,
+                                    use_item.item,
+                                ),
+                            });
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // A charged item does nothing once it runs dry.
+            if let Some(charge) = charges.get_mut(use_item.item) {
+                if charge.current <= 0 {
+                    if witnessed {
+                        gamelog.push(LogEvent::NoCharges {
+                            item: Noun::item(&names, &identification, &enchantments, use_item.item),
+                        });
+                    }
+                    continue;
+                }
+                charge.current -= 1;
+            }
+
+            if foods.get(use_item.item).is_some() {
+                if let Some(clock) = clocks.get_mut(entity) {
+                    clock.state = HungerState::WellFed;
+                    clock.duration = WELL_FED_TURNS;
+                }
+                if witnessed {
+                    gamelog.push(LogEvent::Eat {
+                        user: Noun::of(&names, entity, *player_entity),
+                        item: Noun::item(&names, &identification, &enchantments, use_item.item),
+                    });
+                }
+            }
+
+            // Only one comes off a stack.
+            if consumables.get(use_item.item).is_some() {
+                match quantities.get_mut(use_item.item) {
+                    Some(stack) if stack.amount > 1 => stack.amount -= 1,
+                    _ => entities.delete(use_item.item).expect("Delete failed"),
                 }
             }
 
             let mut targets: Vec<Entity> = Vec::new();
             match use_item.target {
                 None => {
-                    targets.push(*player_entity);
+                    targets.push(entity);
                 }
                 Some(target) => {
                     let area_effect = aoe.get(use_item.item);
@@ -102,6 +331,7 @@ impl<'a> System<'a> for ItemUseSystem {
                             }
                         }
                         Some(area_effect) => {
+                            noises.emit(target, area_effect.radius + BLAST_NOISE_RADIUS);
                             let mut blast_tiles = bracket_lib::prelude::field_of_view(
                                 target,
                                 area_effect.radius,
@@ -131,11 +361,17 @@ impl<'a> System<'a> for ItemUseSystem {
                             None => {}
                             Some(stats) => {
                                 stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
-                                if entity == *player_entity {
-                                    gamelog.entries.push(GameLog::heal_log(
-                                        &names.get(use_item.item).unwrap().name,
-                                        healer.heal_amount,
-                                    ));
+                                if witnessed {
+                                    gamelog.push(LogEvent::Heal {
+                                        user: Noun::of(&names, entity, *player_entity),
+                                        item: Noun::item(
+                                            &names,
+                                            &identification,
+                                            &enchantments,
+                                            use_item.item,
+                                        ),
+                                        amount: healer.heal_amount,
+                                    });
                                 }
                             }
                         }
@@ -143,20 +379,111 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
+            // Monster abilities aren't items and are announced by MonsterAI.
+            if witnessed && heal_items.get(use_item.item).is_none() && items.contains(use_item.item)
+            {
+                gamelog.push(LogEvent::UseItem {
+                    user: Noun::of(&names, entity, *player_entity),
+                    item: Noun::item(&names, &identification, &enchantments, use_item.item),
+                });
+            }
+
+            if identifies.get(use_item.item).is_some() {
+                let unknown = (&entities, &backpack, &slots, &names)
+                    .join()
+                    .filter(|(item, pack, _, name)| {
+                        let hidden_enchantment = enchantments.get(*item).is_some_and(|e| !e.known);
+                        pack.owner == entity
+                            && *item != use_item.item
+                            && (!identification.is_known(&name.name) || hidden_enchantment)
+                    })
+                    .min_by_key(|(_, _, slot, _)| slot.slot)
+                    .map(|(item, _, _, name)| (item, name.name.to_string()));
+                match unknown {
+                    Some((item, name)) => {
+                        let before = Noun::item(&names, &identification, &enchantments, item);
+                        identification.learn(&name);
+                        if let Some(enchantment) = enchantments.get_mut(item) {
+                            enchantment.known = true;
+                        }
+                        if witnessed {
+                            gamelog.push(LogEvent::Identify {
+                                unknown: before,
+                                item: Noun::item(&names, &identification, &enchantments, item),
+                            });
+                        }
+                    }
+                    None => {
+                        if entity == *player_entity {
+                            gamelog.push(LogEvent::NothingToIdentify);
+                        }
+                    }
+                }
+            }
+
+            if recharges.get(use_item.item).is_some() {
+                let drained = (&entities, &backpack, &slots, &charges)
+                    .join()
+                    .filter(|(_, pack, _, charge)| {
+                        pack.owner == entity && charge.current < charge.max
+                    })
+                    .min_by_key(|(_, _, slot, _)| slot.slot)
+                    .map(|(item, _, _, _)| item);
+                match drained {
+                    Some(item) => {
+                        if let Some(charge) = charges.get_mut(item) {
+                            charge.current = charge.max;
+                        }
+                        if witnessed {
+                            gamelog.push(LogEvent::Recharge {
+                                item: Noun::item(&names, &identification, &enchantments, item),
+                            });
+                        }
+                    }
+                    None => {
+                        if entity == *player_entity {
+                            gamelog.push(LogEvent::NothingToRecharge);
+                        }
+                    }
+                }
+            }
+
+            if removes_curse.get(use_item.item).is_some() {
+                let uncursed: Vec<Entity> = (&entities, &backpack, &cursed)
+                    .join()
+                    .filter(|(_, pack, _)| pack.owner == entity)
+                    .map(|(item, _, _)| item)
+                    .collect();
+                if uncursed.is_empty() && entity == *player_entity {
+                    gamelog.push(LogEvent::NothingToUncurse);
+                }
+                for item in uncursed {
+                    cursed.remove(item);
+                    if witnessed {
+                        gamelog.push(LogEvent::Uncurse {
+                            item: Noun::item(&names, &identification, &enchantments, item),
+                        });
+                    }
+                }
+            }
+
             let item_damages = inflict_damage.get(use_item.item);
             match item_damages {
                 None => {}
                 Some(damage) => {
                     for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
-                        if entity == *player_entity {
-                            let player_name = names.get(*player_entity).unwrap();
-                            let mob_name = names.get(*mob).unwrap();
-                            gamelog.entries.push(GameLog::battle_log(
-                                &player_name.name,
-                                &mob_name.name,
-                                damage.damage,
-                            ));
+                        SufferDamage::new_damage(
+                            &mut suffer_damage,
+                            *mob,
+                            damage.damage,
+                            damage.kind,
+                        );
+                        if witnessed || seen_by_player(&map, positions.get(*mob)) {
+                            gamelog.push(LogEvent::Attack {
+                                attacker: Noun::of(&names, entity, *player_entity),
+                                target: Noun::of(&names, *mob, *player_entity),
+                                damage: damage.damage,
+                            });
                         }
                     }
                 }
@@ -170,8 +497,11 @@ impl<'a> System<'a> for ItemUseSystem {
                     Some(paralyze) => {
                         for mob in targets.iter() {
                             add_paralyze.push((*mob, paralyze.turns));
-                            let mob_name = names.get(*mob).unwrap();
-                            gamelog.entries.push(GameLog::paralyze_log(&mob_name.name));
+                            if witnessed || seen_by_player(&map, positions.get(*mob)) {
+                                gamelog.push(LogEvent::Paralyze {
+                                    target: Noun::of(&names, *mob, *player_entity),
+                                });
+                            }
                         }
                     }
                 }
@@ -179,6 +509,18 @@ impl<'a> System<'a> for ItemUseSystem {
             for mob in add_paralyze.iter() {
                 paralyze.insert(mob.0, Paralyze { turns: mob.1 }).unwrap();
             }
+
+            // Seeing what an item does gives its type away.
+            if let Some(name) = names.get(use_item.item) {
+                if witnessed && !identification.is_known(&name.name) {
+                    let before = Noun::item(&names, &identification, &enchantments, use_item.item);
+                    identification.learn(&name.name);
+                    gamelog.push(LogEvent::Identify {
+                        unknown: before,
+                        item: Noun::item(&names, &identification, &enchantments, use_item.item),
+                    });
+                }
+            }
         }
         wants_use.clear();
     }
@@ -195,6 +537,11 @@ impl<'a> System<'a> for ItemDropSystem {
         ReadStorage<'a, Name>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, InventorySlot>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, Equipped>,
+        ReadExpect<'a, Identification>,
+        ReadStorage<'a, Enchantment>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -206,6 +553,11 @@ impl<'a> System<'a> for ItemDropSystem {
             names,
             mut positions,
             mut backpack,
+            mut slots,
+            map,
+            mut equipped,
+            identification,
+            enchantments,
         ) = data;
         for (entity, to_drop) in (&entities, &wants_drop).join() {
             let mut dropper_pos: Position = Position { x: 0, y: 0 };
@@ -224,11 +576,14 @@ impl<'a> System<'a> for ItemDropSystem {
                 )
                 .expect("Unable to insert position");
             backpack.remove(to_drop.item);
+            slots.remove(to_drop.item);
+            equipped.remove(to_drop.item);
 
-            if entity == *player_entity {
-                gamelog.entries.push(GameLog::drop_item_log(
-                    &names.get(to_drop.item).unwrap().name,
-                ));
+            if entity == *player_entity || seen_by_player(&map, positions.get(entity)) {
+                gamelog.push(LogEvent::Drop {
+                    actor: Noun::of(&names, entity, *player_entity),
+                    item: Noun::item(&names, &identification, &enchantments, to_drop.item),
+                });
             }
         }
         wants_drop.clear();
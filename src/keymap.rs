@@ -0,0 +1,562 @@
+use bracket_lib::prelude::console;
+use ggez::event::{KeyCode, KeyMods};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const KEYMAP_FILE: &str = "./keymap.json";
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Command {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveUpLeft,
+    MoveUpRight,
+    MoveDownLeft,
+    MoveDownRight,
+    Wait,
+    DownStairs,
+    PickUp,
+    Drop,
+    Fire,
+    Throw,
+    Inventory,
+    ToggleRenderMode,
+    Cancel,
+    Confirm,
+    NextTarget,
+    Look,
+    MessageLog,
+    Options,
+}
+
+impl Command {
+    pub fn move_delta(self) -> Option<(i32, i32)> {
+        match self {
+            Command::MoveLeft => Some((-1, 0)),
+            Command::MoveRight => Some((1, 0)),
+            Command::MoveUp => Some((0, -1)),
+            Command::MoveDown => Some((0, 1)),
+            Command::MoveUpLeft => Some((-1, -1)),
+            Command::MoveUpRight => Some((1, -1)),
+            Command::MoveDownLeft => Some((-1, 1)),
+            Command::MoveDownRight => Some((1, 1)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum KeyPreset {
+    Default,
+    Vi,
+    Numpad,
+}
+
+// One line of keymap.json: {"key": "H", "mods": "NONE", "command": "MoveLeft"}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: String,
+    #[serde(default = "KeyBinding::no_mods")]
+    pub mods: String,
+    pub command: Command,
+}
+
+impl KeyBinding {
+    fn no_mods() -> String {
+        "NONE".to_string()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyMapConfig {
+    pub preset: KeyPreset,
+    #[serde(default)]
+    pub bindings: Vec<KeyBinding>,
+}
+
+pub struct KeyMap {
+    bindings: HashMap<(KeyCode, KeyMods), Command>,
+}
+
+impl KeyMap {
+    pub fn new(preset: KeyPreset) -> KeyMap {
+        let mut keymap = KeyMap {
+            bindings: HashMap::new(),
+        };
+        keymap.bind_default();
+        match preset {
+            KeyPreset::Default => {}
+            KeyPreset::Vi => keymap.bind_vi(),
+            KeyPreset::Numpad => keymap.bind_numpad(),
+        }
+        keymap
+    }
+
+    // Loads the user keymap, falling back to the default preset when
+    // the file is missing or broken.
+    pub fn load() -> KeyMap {
+        if !Path::new(KEYMAP_FILE).exists() {
+            return KeyMap::new(KeyPreset::Default);
+        }
+        let data = match fs::read_to_string(KEYMAP_FILE) {
+            Ok(data) => data,
+            Err(e) => {
+                console::log(format!("Cannot read {}: {}", KEYMAP_FILE, e));
+                return KeyMap::new(KeyPreset::Default);
+            }
+        };
+        match serde_json::from_str::<KeyMapConfig>(&data) {
+            Ok(config) => {
+                let (keymap, errors) = KeyMap::from_config(&config);
+                for error in errors.iter() {
+                    console::log(error);
+                }
+                keymap
+            }
+            Err(e) => {
+                console::log(format!("Cannot parse {}: {}", KEYMAP_FILE, e));
+                KeyMap::new(KeyPreset::Default)
+            }
+        }
+    }
+
+    // Builds a keymap from the preset plus user bindings. User bindings
+    // override the preset, but two user bindings for the same key are a
+    // conflict and the later one is rejected. So is a binding that takes a
+    // preset key away from its command without binding that command elsewhere,
+    // which would leave it unreachable.
+    pub fn from_config(config: &KeyMapConfig) -> (KeyMap, Vec<String>) {
+        let preset = KeyMap::new(config.preset);
+        let mut errors: Vec<String> = Vec::new();
+        let mut user_bound: HashMap<(KeyCode, KeyMods), Command> = HashMap::new();
+
+        let mut parsed: Vec<(KeyCode, KeyMods, &KeyBinding)> = Vec::new();
+        for binding in config.bindings.iter() {
+            let key = match keycode_from_str(&binding.key) {
+                Some(key) => key,
+                None => {
+                    errors.push(format!("Unknown key \"{}\"", binding.key));
+                    continue;
+                }
+            };
+            let mods = match keymods_from_str(&binding.mods) {
+                Some(mods) => mods,
+                None => {
+                    errors.push(format!("Unknown modifier \"{}\"", binding.mods));
+                    continue;
+                }
+            };
+            parsed.push((key, mods, binding));
+        }
+
+        let mut accepted: Vec<(KeyCode, KeyMods, &KeyBinding)> = Vec::new();
+        for (key, mods, binding) in parsed {
+            if let Some(command) = user_bound.get(&(key, mods)) {
+                if *command != binding.command {
+                    errors.push(format!(
+                        "\"{}+{}\" is bound to both {:?} and {:?}",
+                        binding.mods, binding.key, command, binding.command
+                    ));
+                }
+                continue;
+            }
+            user_bound.insert((key, mods), binding.command);
+            accepted.push((key, mods, binding));
+        }
+
+        // A preset key can be taken over as long as its command still has a
+        // key once everything else is bound. Dropping one binding can strand
+        // or free another command, so drop the first offender and look again.
+        loop {
+            let mut keymap = KeyMap::new(config.preset);
+            for (key, mods, binding) in accepted.iter() {
+                keymap.bind(*key, *mods, binding.command);
+            }
+            let offender = accepted.iter().position(|(key, mods, binding)| {
+                preset
+                    .lookup(*key, *mods)
+                    .is_some_and(|shadowed| shadowed != binding.command && !keymap.binds(shadowed))
+            });
+            match offender {
+                None => return (keymap, errors),
+                Some(i) => {
+                    let (key, mods, binding) = accepted.remove(i);
+                    let shadowed = preset.lookup(key, mods).unwrap();
+                    errors.push(format!(
+                        "\"{}+{}\" is the preset key for {:?}; bind {:?} to another key first",
+                        binding.mods, binding.key, shadowed, shadowed
+                    ));
+                }
+            }
+        }
+    }
+
+    fn binds(&self, command: Command) -> bool {
+        self.bindings.values().any(|bound| *bound == command)
+    }
+
+    pub fn bind(&mut self, key: KeyCode, mods: KeyMods, command: Command) {
+        self.bindings.insert((key, mods), command);
+    }
+
+    pub fn lookup(&self, key: KeyCode, mods: KeyMods) -> Option<Command> {
+        self.bindings.get(&(key, mods)).copied()
+    }
+
+    fn bind_default(&mut self) {
+        self.bind(KeyCode::Left, KeyMods::NONE, Command::MoveLeft);
+        self.bind(KeyCode::Right, KeyMods::NONE, Command::MoveRight);
+        self.bind(KeyCode::Up, KeyMods::NONE, Command::MoveUp);
+        self.bind(KeyCode::Down, KeyMods::NONE, Command::MoveDown);
+        self.bind(KeyCode::Left, KeyMods::SHIFT, Command::MoveUpLeft);
+        self.bind(KeyCode::Right, KeyMods::SHIFT, Command::MoveUpRight);
+        self.bind(KeyCode::Left, KeyMods::CTRL, Command::MoveDownLeft);
+        self.bind(KeyCode::Right, KeyMods::CTRL, Command::MoveDownRight);
+        self.bind(KeyCode::Period, KeyMods::SHIFT, Command::DownStairs);
+        self.bind(KeyCode::Comma, KeyMods::NONE, Command::Wait);
+        self.bind(KeyCode::G, KeyMods::NONE, Command::PickUp);
+        self.bind(KeyCode::D, KeyMods::NONE, Command::Drop);
+        self.bind(KeyCode::F, KeyMods::NONE, Command::Fire);
+        self.bind(KeyCode::T, KeyMods::NONE, Command::Throw);
+        self.bind(KeyCode::I, KeyMods::NONE, Command::Inventory);
+        self.bind(KeyCode::F11, KeyMods::NONE, Command::ToggleRenderMode);
+        self.bind(KeyCode::Escape, KeyMods::NONE, Command::Cancel);
+        self.bind(KeyCode::Return, KeyMods::NONE, Command::Confirm);
+        self.bind(KeyCode::NumpadEnter, KeyMods::NONE, Command::Confirm);
+        self.bind(KeyCode::Tab, KeyMods::NONE, Command::NextTarget);
+        self.bind(KeyCode::X, KeyMods::NONE, Command::Look);
+        self.bind(KeyCode::M, KeyMods::NONE, Command::MessageLog);
+        self.bind(KeyCode::O, KeyMods::NONE, Command::Options);
+    }
+
+    fn bind_vi(&mut self) {
+        self.bind(KeyCode::H, KeyMods::NONE, Command::MoveLeft);
+        self.bind(KeyCode::L, KeyMods::NONE, Command::MoveRight);
+        self.bind(KeyCode::K, KeyMods::NONE, Command::MoveUp);
+        self.bind(KeyCode::J, KeyMods::NONE, Command::MoveDown);
+        self.bind(KeyCode::Y, KeyMods::NONE, Command::MoveUpLeft);
+        self.bind(KeyCode::U, KeyMods::NONE, Command::MoveUpRight);
+        self.bind(KeyCode::B, KeyMods::NONE, Command::MoveDownLeft);
+        self.bind(KeyCode::N, KeyMods::NONE, Command::MoveDownRight);
+        self.bind(KeyCode::Period, KeyMods::NONE, Command::Wait);
+    }
+
+    fn bind_numpad(&mut self) {
+        self.bind(KeyCode::Numpad4, KeyMods::NONE, Command::MoveLeft);
+        self.bind(KeyCode::Numpad6, KeyMods::NONE, Command::MoveRight);
+        self.bind(KeyCode::Numpad8, KeyMods::NONE, Command::MoveUp);
+        self.bind(KeyCode::Numpad2, KeyMods::NONE, Command::MoveDown);
+        self.bind(KeyCode::Numpad7, KeyMods::NONE, Command::MoveUpLeft);
+        self.bind(KeyCode::Numpad9, KeyMods::NONE, Command::MoveUpRight);
+        self.bind(KeyCode::Numpad1, KeyMods::NONE, Command::MoveDownLeft);
+        self.bind(KeyCode::Numpad3, KeyMods::NONE, Command::MoveDownRight);
+        self.bind(KeyCode::Numpad5, KeyMods::NONE, Command::Wait);
+    }
+}
+
+// Inventory letters: "a" -> 0 ... "z" -> 25. Anything else is not a letter.
+pub fn keycode_to_letter(key: KeyCode) -> Option<i32> {
+    let letter = match key {
+        KeyCode::A => 0,
+        KeyCode::B => 1,
+        KeyCode::C => 2,
+        KeyCode::D => 3,
+        KeyCode::E => 4,
+        KeyCode::F => 5,
+        KeyCode::G => 6,
+        KeyCode::H => 7,
+        KeyCode::I => 8,
+        KeyCode::J => 9,
+        KeyCode::K => 10,
+        KeyCode::L => 11,
+        KeyCode::M => 12,
+        KeyCode::N => 13,
+        KeyCode::O => 14,
+        KeyCode::P => 15,
+        KeyCode::Q => 16,
+        KeyCode::R => 17,
+        KeyCode::S => 18,
+        KeyCode::T => 19,
+        KeyCode::U => 20,
+        KeyCode::V => 21,
+        KeyCode::W => 22,
+        KeyCode::X => 23,
+        KeyCode::Y => 24,
+        KeyCode::Z => 25,
+        _ => return None,
+    };
+    Some(letter)
+}
+
+pub fn keymods_from_str(s: &str) -> Option<KeyMods> {
+    let mut mods = KeyMods::NONE;
+    for part in s.split('+') {
+        match part.trim().to_uppercase().as_ref() {
+            "NONE" | "" => {}
+            "SHIFT" => mods |= KeyMods::SHIFT,
+            "CTRL" => mods |= KeyMods::CTRL,
+            "ALT" => mods |= KeyMods::ALT,
+            "LOGO" => mods |= KeyMods::LOGO,
+            _ => return None,
+        }
+    }
+    Some(mods)
+}
+
+pub fn keycode_from_str(s: &str) -> Option<KeyCode> {
+    let key = match s.to_uppercase().as_ref() {
+        "A" => KeyCode::A,
+        "B" => KeyCode::B,
+        "C" => KeyCode::C,
+        "D" => KeyCode::D,
+        "E" => KeyCode::E,
+        "F" => KeyCode::F,
+        "G" => KeyCode::G,
+        "H" => KeyCode::H,
+        "I" => KeyCode::I,
+        "J" => KeyCode::J,
+        "K" => KeyCode::K,
+        "L" => KeyCode::L,
+        "M" => KeyCode::M,
+        "N" => KeyCode::N,
+        "O" => KeyCode::O,
+        "P" => KeyCode::P,
+        "Q" => KeyCode::Q,
+        "R" => KeyCode::R,
+        "S" => KeyCode::S,
+        "T" => KeyCode::T,
+        "U" => KeyCode::U,
+        "V" => KeyCode::V,
+        "W" => KeyCode::W,
+        "X" => KeyCode::X,
+        "Y" => KeyCode::Y,
+        "Z" => KeyCode::Z,
+        "0" => KeyCode::Key0,
+        "1" => KeyCode::Key1,
+        "2" => KeyCode::Key2,
+        "3" => KeyCode::Key3,
+        "4" => KeyCode::Key4,
+        "5" => KeyCode::Key5,
+        "6" => KeyCode::Key6,
+        "7" => KeyCode::Key7,
+        "8" => KeyCode::Key8,
+        "9" => KeyCode::Key9,
+        "NUMPAD0" => KeyCode::Numpad0,
+        "NUMPAD1" => KeyCode::Numpad1,
+        "NUMPAD2" => KeyCode::Numpad2,
+        "NUMPAD3" => KeyCode::Numpad3,
+        "NUMPAD4" => KeyCode::Numpad4,
+        "NUMPAD5" => KeyCode::Numpad5,
+        "NUMPAD6" => KeyCode::Numpad6,
+        "NUMPAD7" => KeyCode::Numpad7,
+        "NUMPAD8" => KeyCode::Numpad8,
+        "NUMPAD9" => KeyCode::Numpad9,
+        "LEFT" => KeyCode::Left,
+        "RIGHT" => KeyCode::Right,
+        "UP" => KeyCode::Up,
+        "DOWN" => KeyCode::Down,
+        "ESCAPE" => KeyCode::Escape,
+        "RETURN" | "ENTER" => KeyCode::Return,
+        "TAB" => KeyCode::Tab,
+        "SPACE" => KeyCode::Space,
+        "PERIOD" | "." => KeyCode::Period,
+        "COMMA" | "," => KeyCode::Comma,
+        "SLASH" | "/" => KeyCode::Slash,
+        "SEMICOLON" | ";" => KeyCode::Semicolon,
+        "F1" => KeyCode::F1,
+        "F2" => KeyCode::F2,
+        "F3" => KeyCode::F3,
+        "F4" => KeyCode::F4,
+        "F5" => KeyCode::F5,
+        "F6" => KeyCode::F6,
+        "F7" => KeyCode::F7,
+        "F8" => KeyCode::F8,
+        "F9" => KeyCode::F9,
+        "F10" => KeyCode::F10,
+        "F11" => KeyCode::F11,
+        "F12" => KeyCode::F12,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(key: &str, mods: &str, command: Command) -> KeyBinding {
+        KeyBinding {
+            key: key.to_string(),
+            mods: mods.to_string(),
+            command,
+        }
+    }
+
+    fn config(preset: KeyPreset, bindings: Vec<KeyBinding>) -> KeyMapConfig {
+        KeyMapConfig { preset, bindings }
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(keycode_from_str("h"), Some(KeyCode::H));
+        assert_eq!(keycode_from_str("H"), Some(KeyCode::H));
+        assert_eq!(keycode_from_str("numpad7"), Some(KeyCode::Numpad7));
+        assert_eq!(keycode_from_str("Enter"), Some(KeyCode::Return));
+        assert_eq!(keycode_from_str("."), Some(KeyCode::Period));
+        assert_eq!(keycode_from_str("F12"), Some(KeyCode::F12));
+        assert_eq!(keycode_from_str("F13"), None);
+        assert_eq!(keycode_from_str(""), None);
+        assert_eq!(keycode_from_str("HH"), None);
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert_eq!(keymods_from_str("NONE"), Some(KeyMods::NONE));
+        assert_eq!(keymods_from_str(""), Some(KeyMods::NONE));
+        assert_eq!(keymods_from_str("shift"), Some(KeyMods::SHIFT));
+        assert_eq!(
+            keymods_from_str("Shift + Ctrl"),
+            Some(KeyMods::SHIFT | KeyMods::CTRL)
+        );
+        assert_eq!(keymods_from_str("HYPER"), None);
+    }
+
+    #[test]
+    fn user_binding_adds_to_the_preset() {
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![binding("Q", "NONE", Command::Wait)],
+        ));
+        assert!(errors.is_empty());
+        assert_eq!(
+            keymap.lookup(KeyCode::Q, KeyMods::NONE),
+            Some(Command::Wait)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Comma, KeyMods::NONE),
+            Some(Command::Wait)
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_reported() {
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![
+                binding("Nope", "NONE", Command::Wait),
+                binding("Q", "Hyper", Command::Wait),
+            ],
+        ));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(keymap.lookup(KeyCode::Q, KeyMods::NONE), None);
+    }
+
+    #[test]
+    fn two_user_bindings_for_one_key_conflict() {
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![
+                binding("Q", "NONE", Command::Wait),
+                binding("Q", "NONE", Command::Look),
+            ],
+        ));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            keymap.lookup(KeyCode::Q, KeyMods::NONE),
+            Some(Command::Wait)
+        );
+    }
+
+    #[test]
+    fn repeating_a_user_binding_is_not_a_conflict() {
+        let (_, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![
+                binding("Q", "NONE", Command::Wait),
+                binding("q", "NONE", Command::Wait),
+            ],
+        ));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn shadowing_a_preset_key_is_rejected() {
+        // "G" is the only PickUp key; taking it would leave PickUp unbound.
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![binding("G", "NONE", Command::Look)],
+        ));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            keymap.lookup(KeyCode::G, KeyMods::NONE),
+            Some(Command::PickUp)
+        );
+    }
+
+    #[test]
+    fn shadowing_is_fine_when_the_command_moves() {
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![
+                binding("G", "NONE", Command::Look),
+                binding("P", "NONE", Command::PickUp),
+            ],
+        ));
+        assert!(errors.is_empty());
+        assert_eq!(
+            keymap.lookup(KeyCode::G, KeyMods::NONE),
+            Some(Command::Look)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::P, KeyMods::NONE),
+            Some(Command::PickUp)
+        );
+    }
+
+    #[test]
+    fn rebinding_a_preset_key_to_its_own_command_is_fine() {
+        let (_, errors) = KeyMap::from_config(&config(
+            KeyPreset::Vi,
+            vec![binding("H", "NONE", Command::MoveLeft)],
+        ));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn shadowing_is_fine_when_the_preset_has_another_key() {
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Vi,
+            vec![binding("PERIOD", "NONE", Command::Look)],
+        ));
+        assert!(errors.is_empty());
+        assert_eq!(
+            keymap.lookup(KeyCode::Period, KeyMods::NONE),
+            Some(Command::Look)
+        );
+        assert!(keymap.binds(Command::Wait));
+    }
+
+    #[test]
+    fn a_rejected_binding_does_not_count_as_a_move() {
+        let (keymap, errors) = KeyMap::from_config(&config(
+            KeyPreset::Default,
+            vec![
+                binding("G", "NONE", Command::Look),
+                binding("D", "NONE", Command::PickUp),
+            ],
+        ));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            keymap.lookup(KeyCode::G, KeyMods::NONE),
+            Some(Command::PickUp)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::D, KeyMods::NONE),
+            Some(Command::Drop)
+        );
+    }
+}
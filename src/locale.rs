@@ -0,0 +1,749 @@
+use super::gamelog::{LogEvent, Noun};
+use super::{AiState, AmmoKind, DamageType, HungerState};
+use ggez::graphics::{Color, TextFragment};
+use serde::{Deserialize, Serialize};
+
+pub const NAME_COLOR: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+pub const DEAD_COLOR: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+pub const DAMAGE_COLOR: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+pub const STATUS_COLOR: Color = Color::new(0.5, 0.5, 1.0, 1.0);
+pub const SYSTEM_COLOR: Color = Color::new(0.5, 0.5, 0.5, 1.0);
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Locale {
+    Ja,
+    En,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::Ja, Locale::En];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Locale::Ja => "日本語",
+            Locale::En => "English",
+        }
+    }
+}
+
+// Fixed strings used by the menus and windows.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum UiText {
+    Title,
+    Welcome,
+    Start,
+    LoadGame,
+    Options,
+    Quit,
+    Language,
+    Close,
+    Inventory,
+    Equipped,
+    Cursed,
+    Burdened,
+    DropHowMany,
+    Trade,
+    Buy,
+    Sell,
+    Gold,
+    Look,
+    MessageLog,
+    Search,
+    History,
+    Combat,
+    Items,
+    Movement,
+    System,
+    Player,
+    Equipment,
+    Weapon,
+    Shield,
+    Equip,
+    Hp,
+    Power,
+    Defense,
+    Damage,
+    Heal,
+    Range,
+    Radius,
+    Paralyze,
+    Paralyzed,
+    Turns,
+    Consumable,
+    State,
+    Resist,
+    Weak,
+    Ranged,
+    Unequipped,
+    Ammo,
+    Count,
+    Unidentified,
+    Charges,
+    Weight,
+    Value,
+    Nothing,
+    Unexplored,
+    NotInSight,
+    Wall,
+    Floor,
+    DownStairs,
+}
+
+pub fn ui_text(text: UiText, locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => match text {
+            UiText::Title => "Sushy -- 典型的ローグライク!!",
+            UiText::Welcome => "ようこそ、Sushyeの世界へ！",
+            UiText::Start => "はじめから",
+            UiText::LoadGame => "つづきから",
+            UiText::Options => "設定",
+            UiText::Quit => "終了",
+            UiText::Language => "言語",
+            UiText::Close => "閉じる",
+            UiText::Inventory => "所持品",
+            UiText::Equipped => "装備中",
+            UiText::Cursed => "呪い",
+            UiText::Burdened => "重荷",
+            UiText::DropHowMany => "いくつ置く？",
+            UiText::Trade => "取引",
+            UiText::Buy => "買う",
+            UiText::Sell => "売る",
+            UiText::Gold => "所持金",
+            UiText::Look => "調べる",
+            UiText::MessageLog => "メッセージログ",
+            UiText::Search => "検索",
+            UiText::History => "履歴",
+            UiText::Combat => "戦闘",
+            UiText::Items => "アイテム",
+            UiText::Movement => "移動",
+            UiText::System => "システム",
+            UiText::Player => "プレイヤー",
+            UiText::Equipment => "装備",
+            UiText::Weapon => "武器",
+            UiText::Shield => "盾",
+            UiText::Equip => "装備",
+            UiText::Hp => "HP",
+            UiText::Power => "攻撃力",
+            UiText::Defense => "防御力",
+            UiText::Damage => "ダメージ",
+            UiText::Heal => "回復",
+            UiText::Range => "射程",
+            UiText::Radius => "範囲",
+            UiText::Paralyze => "麻痺",
+            UiText::Paralyzed => "麻痺中",
+            UiText::Turns => "ターン",
+            UiText::Consumable => "消耗品",
+            UiText::State => "状態",
+            UiText::Resist => "耐性",
+            UiText::Weak => "弱点",
+            UiText::Ranged => "射撃",
+            UiText::Unequipped => "なし",
+            UiText::Ammo => "弾",
+            UiText::Count => "個数",
+            UiText::Unidentified => "未識別",
+            UiText::Charges => "回数",
+            UiText::Weight => "重さ",
+            UiText::Value => "価値",
+            UiText::Nothing => "何もない",
+            UiText::Unexplored => "未踏",
+            UiText::NotInSight => "見えない",
+            UiText::Wall => "壁",
+            UiText::Floor => "床",
+            UiText::DownStairs => "下り階段",
+        },
+        Locale::En => match text {
+            UiText::Title => "Sushy -- Typical Roguelike!!",
+            UiText::Welcome => "Welcome to the world of Sushye!",
+            UiText::Start => "Start",
+            UiText::LoadGame => "Load Game",
+            UiText::Options => "Options",
+            UiText::Quit => "Quit",
+            UiText::Language => "Language",
+            UiText::Close => "Close",
+            UiText::Inventory => "Inventory",
+            UiText::Equipped => "equipped",
+            UiText::Cursed => "cursed",
+            UiText::Burdened => "Burdened",
+            UiText::DropHowMany => "Drop how many?",
+            UiText::Trade => "Trade",
+            UiText::Buy => "Buy",
+            UiText::Sell => "Sell",
+            UiText::Gold => "Gold",
+            UiText::Look => "Look",
+            UiText::MessageLog => "Message Log",
+            UiText::Search => "Search",
+            UiText::History => "History",
+            UiText::Combat => "Combat",
+            UiText::Items => "Items",
+            UiText::Movement => "Movement",
+            UiText::System => "System",
+            UiText::Player => "Player",
+            UiText::Equipment => "Equipment",
+            UiText::Weapon => "Weapon",
+            UiText::Shield => "Shield",
+            UiText::Equip => "Equip",
+            UiText::Hp => "HP",
+            UiText::Power => "Power",
+            UiText::Defense => "Defense",
+            UiText::Damage => "Damage",
+            UiText::Heal => "Heal",
+            UiText::Range => "Range",
+            UiText::Radius => "Radius",
+            UiText::Paralyze => "Paralyze",
+            UiText::Paralyzed => "Paralyzed",
+            UiText::Turns => "turns",
+            UiText::Consumable => "Consumable",
+            UiText::State => "State",
+            UiText::Resist => "Resist",
+            UiText::Weak => "Weak",
+            UiText::Ranged => "Ranged",
+            UiText::Unequipped => "None",
+            UiText::Ammo => "Ammo",
+            UiText::Count => "Count",
+            UiText::Unidentified => "Unidentified",
+            UiText::Charges => "Charges",
+            UiText::Weight => "Weight",
+            UiText::Value => "Value",
+            UiText::Nothing => "Nothing",
+            UiText::Unexplored => "Unexplored",
+            UiText::NotInSight => "Not in sight",
+            UiText::Wall => "Wall",
+            UiText::Floor => "Floor",
+            UiText::DownStairs => "Down stairs",
+        },
+    }
+}
+
+fn name(noun: &Noun) -> TextFragment {
+    TextFragment::new(noun.name.to_string()).color(NAME_COLOR)
+}
+
+fn plain<S: ToString>(text: S) -> TextFragment {
+    TextFragment::new(text.to_string())
+}
+
+// "あなた" stands in for the player in Japanese.
+fn ja_subject(noun: &Noun) -> TextFragment {
+    if noun.is_player {
+        plain("あなた")
+    } else {
+        name(noun)
+    }
+}
+
+// English needs "You" at the start of a sentence and "you" elsewhere.
+fn en_subject(noun: &Noun) -> TextFragment {
+    if noun.is_player {
+        plain("You")
+    } else {
+        name(noun)
+    }
+}
+
+fn en_object(noun: &Noun) -> TextFragment {
+    if noun.is_player {
+        plain("you")
+    } else {
+        name(noun)
+    }
+}
+
+// Third person gets the "s": "You hit" but "Kobolso hits".
+fn en_verb(noun: &Noun, verb: &str) -> String {
+    if noun.is_player {
+        verb.to_string()
+    } else if verb.ends_with('s') || verb.ends_with("sh") {
+        format!("{}es", verb)
+    } else {
+        format!("{}s", verb)
+    }
+}
+
+pub fn damage_type_name(kind: DamageType, locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => match kind {
+            DamageType::Physical => "物理",
+            DamageType::Fire => "炎",
+            DamageType::Cold => "冷気",
+            DamageType::Poison => "毒",
+            DamageType::Magic => "魔法",
+        },
+        Locale::En => match kind {
+            DamageType::Physical => "physical",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+            DamageType::Magic => "magic",
+        },
+    }
+}
+
+pub fn hunger_state_name(state: HungerState, locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => match state {
+            HungerState::WellFed => "満腹",
+            HungerState::Normal => "普通",
+            HungerState::Hungry => "空腹",
+            HungerState::Starving => "飢餓",
+        },
+        Locale::En => match state {
+            HungerState::WellFed => "Well Fed",
+            HungerState::Normal => "Normal",
+            HungerState::Hungry => "Hungry",
+            HungerState::Starving => "Starving",
+        },
+    }
+}
+
+pub fn ammo_kind_name(kind: AmmoKind, locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => match kind {
+            AmmoKind::Arrow => "矢",
+            AmmoKind::Stone => "石",
+        },
+        Locale::En => match kind {
+            AmmoKind::Arrow => "Arrow",
+            AmmoKind::Stone => "Stone",
+        },
+    }
+}
+
+pub fn ai_state_name(state: AiState, locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => match state {
+            AiState::Idle => "待機",
+            AiState::Sleeping => "睡眠",
+            AiState::Wandering => "徘徊",
+            AiState::Hunting => "追跡",
+            AiState::Searching => "捜索",
+            AiState::Fleeing => "逃走",
+        },
+        Locale::En => match state {
+            AiState::Idle => "Idle",
+            AiState::Sleeping => "Sleeping",
+            AiState::Wandering => "Wandering",
+            AiState::Hunting => "Hunting",
+            AiState::Searching => "Searching",
+            AiState::Fleeing => "Fleeing",
+        },
+    }
+}
+
+pub fn render(event: &LogEvent, locale: Locale) -> Vec<TextFragment> {
+    match locale {
+        Locale::Ja => render_ja(event),
+        Locale::En => render_en(event),
+    }
+}
+
+fn render_ja(event: &LogEvent) -> Vec<TextFragment> {
+    match event {
+        LogEvent::Welcome => vec![
+            TextFragment::new("Sushyの世界").color(NAME_COLOR),
+            plain("へようこそ"),
+        ],
+        LogEvent::Dead { victim } => vec![
+            ja_subject(victim).color(DEAD_COLOR),
+            TextFragment::new("は死んだ。").color(DEAD_COLOR),
+        ],
+        LogEvent::PickUp { actor, item } => {
+            let mut line = Vec::new();
+            if !actor.is_player {
+                line.push(name(actor));
+                line.push(plain("は"));
+            }
+            line.push(name(item));
+            line.push(plain("を拾った。"));
+            line
+        }
+        LogEvent::Drop { actor, item } => {
+            let mut line = Vec::new();
+            if !actor.is_player {
+                line.push(name(actor));
+                line.push(plain("は"));
+            }
+            line.push(name(item));
+            line.push(plain("を落とした。"));
+            line
+        }
+        LogEvent::CannotDescend => {
+            vec![TextFragment::new("そこからは降りられない。").color(SYSTEM_COLOR)]
+        }
+        LogEvent::NothingToPickUp => {
+            vec![TextFragment::new("そこには何もない。").color(SYSTEM_COLOR)]
+        }
+        LogEvent::Heal { user, item, amount } => {
+            let mut line = Vec::new();
+            if !user.is_player {
+                line.push(name(user));
+                line.push(plain("は"));
+            }
+            line.push(name(item));
+            line.push(plain("を使った。HPが"));
+            line.push(TextFragment::new(amount.to_string()).color(NAME_COLOR));
+            line.push(plain("回復した。"));
+            line
+        }
+        LogEvent::NoSuchItem => {
+            vec![TextFragment::new("そのようなアイテムを持っていない。").color(SYSTEM_COLOR)]
+        }
+        LogEvent::Equip { actor, item } => vec![
+            ja_subject(actor),
+            plain("は"),
+            name(item),
+            plain("を装備した。"),
+        ],
+        LogEvent::Unequip { actor, item } => vec![
+            ja_subject(actor),
+            plain("は"),
+            name(item),
+            plain("を外した。"),
+        ],
+        LogEvent::Eat { user, item } => vec![
+            ja_subject(user),
+            plain("は"),
+            name(item),
+            plain("を食べた。"),
+        ],
+        LogEvent::Hunger { state } => vec![
+            plain("あなたは"),
+            TextFragment::new(hunger_state_name(*state, Locale::Ja)).color(STATUS_COLOR),
+            plain("状態になった。"),
+        ],
+        LogEvent::NoLauncher => {
+            vec![TextFragment::new("弓もスリングも装備していない。").color(SYSTEM_COLOR)]
+        }
+        LogEvent::NoAmmo => vec![TextFragment::new("撃つ弾がない。").color(SYSTEM_COLOR)],
+        LogEvent::Throw { actor, item, fired } => vec![
+            ja_subject(actor),
+            plain("は"),
+            name(item),
+            plain(if *fired {
+                "を放った。"
+            } else {
+                "を投げた。"
+            }),
+        ],
+        LogEvent::Break { item } => vec![name(item), plain("は壊れた。")],
+        LogEvent::Attack {
+            attacker,
+            target,
+            damage,
+        } => vec![
+            ja_subject(attacker),
+            plain("は"),
+            ja_subject(target),
+            plain("に"),
+            TextFragment::new(damage.to_string()).color(DAMAGE_COLOR),
+            plain("のダメージを与えた。"),
+        ],
+        LogEvent::Miss { attacker, target } => vec![
+            ja_subject(attacker),
+            plain("の攻撃は"),
+            ja_subject(target),
+            plain("に当たらなかった。"),
+        ],
+        LogEvent::Fumble { attacker } => vec![
+            ja_subject(attacker),
+            plain("は"),
+            TextFragment::new("体勢を崩した").color(STATUS_COLOR),
+            plain("！"),
+        ],
+        LogEvent::Critical {
+            attacker,
+            target,
+            damage,
+        } => vec![
+            TextFragment::new("会心の一撃！").color(DAMAGE_COLOR),
+            ja_subject(attacker),
+            plain("は"),
+            ja_subject(target),
+            plain("に"),
+            TextFragment::new(damage.to_string()).color(DAMAGE_COLOR),
+            plain("のダメージを与えた。"),
+        ],
+        LogEvent::Resist { target, kind } => vec![
+            ja_subject(target),
+            plain(format!("は{}に", damage_type_name(*kind, Locale::Ja))),
+            TextFragment::new("耐えた").color(STATUS_COLOR),
+            plain("。"),
+        ],
+        LogEvent::Vulnerable { target, kind } => vec![
+            ja_subject(target),
+            plain(format!("は{}に", damage_type_name(*kind, Locale::Ja))),
+            TextFragment::new("弱い").color(DAMAGE_COLOR),
+            plain("！"),
+        ],
+        LogEvent::Paralyze { target } => vec![
+            ja_subject(target),
+            plain("は"),
+            TextFragment::new("麻痺").color(STATUS_COLOR),
+            plain("した。"),
+        ],
+        LogEvent::Descend => vec![plain("あなたは階段を下ることにした……。")],
+        LogEvent::Flee { actor } => vec![
+            ja_subject(actor),
+            plain("は"),
+            TextFragment::new("逃げ出した").color(STATUS_COLOR),
+            plain("！"),
+        ],
+        LogEvent::Rally { actor } => vec![ja_subject(actor), plain("は戦意を取り戻した。")],
+        LogEvent::WakeUp { actor } => vec![ja_subject(actor), plain("は目を覚ました。")],
+        LogEvent::UseItem { user, item } => vec![
+            ja_subject(user),
+            plain("は"),
+            name(item),
+            plain("を使った。"),
+        ],
+        LogEvent::Identify { unknown, item } => {
+            vec![name(unknown), plain("は"), name(item), plain("だった。")]
+        }
+        LogEvent::NothingToIdentify => vec![plain("識別するものがない。")],
+        LogEvent::NoCharges { item } => vec![name(item), plain("は何も起こさなかった。")],
+        LogEvent::Recharge { item } => vec![name(item), plain("に魔力が満ちた。")],
+        LogEvent::NothingToRecharge => vec![plain("充填するものがない。")],
+        LogEvent::Cursed { item } => vec![
+            name(item),
+            TextFragment::new("は呪われている！").color(DAMAGE_COLOR),
+        ],
+        LogEvent::Uncurse { item } => vec![name(item), plain("の呪いが解けた。")],
+        LogEvent::NothingToUncurse => vec![plain("呪われたものはない。")],
+        LogEvent::TooHeavy { item } => vec![name(item), plain("は重すぎて持てない。")],
+        LogEvent::Burdened => vec![
+            plain("あなたは"),
+            TextFragment::new(ui_text(UiText::Burdened, Locale::Ja)).color(STATUS_COLOR),
+            plain("状態になった。"),
+        ],
+        LogEvent::PickUpGold { amount } => vec![plain(format!("{}ゴールドを拾った。", amount))],
+        LogEvent::Buy { item, price } => {
+            vec![name(item), plain(format!("を{}ゴールドで買った。", price))]
+        }
+        LogEvent::Sell { item, price } => {
+            vec![name(item), plain(format!("を{}ゴールドで売った。", price))]
+        }
+        LogEvent::NotEnoughGold { item } => {
+            vec![name(item), plain("を買うにはお金が足りない。")]
+        }
+        LogEvent::SellEquipped { item } => {
+            vec![name(item), plain("を装備したままでは売れない。")]
+        }
+        LogEvent::Cast { caster, ability } => vec![
+            ja_subject(caster),
+            plain("は"),
+            name(ability),
+            plain("を放った！"),
+        ],
+    }
+}
+
+fn render_en(event: &LogEvent) -> Vec<TextFragment> {
+    match event {
+        LogEvent::Welcome => vec![
+            plain("Welcome to "),
+            TextFragment::new("Sushy World").color(NAME_COLOR),
+        ],
+        LogEvent::Dead { victim } => vec![
+            en_subject(victim).color(DEAD_COLOR),
+            TextFragment::new(format!(" {}.", en_verb(victim, "die"))).color(DEAD_COLOR),
+        ],
+        LogEvent::PickUp { actor, item } => vec![
+            en_subject(actor),
+            plain(format!(" {} up ", en_verb(actor, "pick"))),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::Drop { actor, item } => vec![
+            en_subject(actor),
+            plain(format!(" {} ", en_verb(actor, "drop"))),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::CannotDescend => {
+            vec![TextFragment::new("There is no way down here.").color(SYSTEM_COLOR)]
+        }
+        LogEvent::NothingToPickUp => {
+            vec![TextFragment::new("There is nothing here.").color(SYSTEM_COLOR)]
+        }
+        LogEvent::Heal { user, item, amount } => vec![
+            en_subject(user),
+            plain(format!(" {} ", en_verb(user, "use"))),
+            name(item),
+            plain(format!(" and {} ", en_verb(user, "recover"))),
+            TextFragment::new(amount.to_string()).color(NAME_COLOR),
+            plain(" HP."),
+        ],
+        LogEvent::NoSuchItem => {
+            vec![TextFragment::new("You do not have such an item.").color(SYSTEM_COLOR)]
+        }
+        LogEvent::Equip { actor, item } => vec![
+            en_subject(actor),
+            plain(format!(" {} ", en_verb(actor, "equip"))),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::Unequip { actor, item } => vec![
+            en_subject(actor),
+            plain(format!(" {} off ", en_verb(actor, "take"))),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::Eat { user, item } => vec![
+            en_subject(user),
+            plain(format!(" {} ", en_verb(user, "eat"))),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::Hunger { state } => vec![
+            plain("You are now "),
+            TextFragment::new(hunger_state_name(*state, Locale::En)).color(STATUS_COLOR),
+            plain("."),
+        ],
+        LogEvent::NoLauncher => {
+            vec![TextFragment::new("You have no bow or sling equipped.").color(SYSTEM_COLOR)]
+        }
+        LogEvent::NoAmmo => {
+            vec![TextFragment::new("You have nothing to shoot.").color(SYSTEM_COLOR)]
+        }
+        LogEvent::Throw { actor, item, fired } => vec![
+            en_subject(actor),
+            plain(format!(
+                " {} ",
+                en_verb(actor, if *fired { "shoot" } else { "throw" })
+            )),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::Break { item } => vec![name(item), plain(" breaks.")],
+        LogEvent::Attack {
+            attacker,
+            target,
+            damage,
+        } => vec![
+            en_subject(attacker),
+            plain(format!(" {} ", en_verb(attacker, "hit"))),
+            en_object(target),
+            plain(" for "),
+            TextFragment::new(damage.to_string()).color(DAMAGE_COLOR),
+            plain(if *damage == 1 {
+                " point of damage."
+            } else {
+                " points of damage."
+            }),
+        ],
+        LogEvent::Miss { attacker, target } => vec![
+            en_subject(attacker),
+            plain(format!(" {} ", en_verb(attacker, "miss"))),
+            en_object(target),
+            plain("."),
+        ],
+        LogEvent::Fumble { attacker } => vec![
+            en_subject(attacker),
+            plain(" "),
+            TextFragment::new(en_verb(attacker, "fumble")).color(STATUS_COLOR),
+            plain(" the attack."),
+        ],
+        LogEvent::Critical {
+            attacker,
+            target,
+            damage,
+        } => vec![
+            TextFragment::new("Critical hit! ").color(DAMAGE_COLOR),
+            en_subject(attacker),
+            plain(format!(" {} ", en_verb(attacker, "hit"))),
+            en_object(target),
+            plain(" for "),
+            TextFragment::new(damage.to_string()).color(DAMAGE_COLOR),
+            plain(if *damage == 1 {
+                " point of damage."
+            } else {
+                " points of damage."
+            }),
+        ],
+        LogEvent::Resist { target, kind } => vec![
+            en_subject(target),
+            plain(" "),
+            TextFragment::new("resisted").color(STATUS_COLOR),
+            plain(format!(" the {}.", damage_type_name(*kind, Locale::En))),
+        ],
+        LogEvent::Vulnerable { target, kind } => vec![
+            en_subject(target),
+            plain(if target.is_player { " are " } else { " is " }),
+            TextFragment::new("vulnerable").color(DAMAGE_COLOR),
+            plain(format!(" to {}!", damage_type_name(*kind, Locale::En))),
+        ],
+        LogEvent::Paralyze { target } => vec![
+            en_subject(target),
+            plain(if target.is_player { " are " } else { " is " }),
+            TextFragment::new("paralyzed").color(STATUS_COLOR),
+            plain("."),
+        ],
+        LogEvent::Descend => vec![plain("You decide to go down the stairs...")],
+        LogEvent::Flee { actor } => vec![
+            en_subject(actor),
+            plain(format!(" {} to ", en_verb(actor, "turn"))),
+            TextFragment::new("flee").color(STATUS_COLOR),
+            plain("!"),
+        ],
+        LogEvent::Rally { actor } => vec![
+            en_subject(actor),
+            plain(format!(" {} its courage.", en_verb(actor, "regain"))),
+        ],
+        LogEvent::WakeUp { actor } => vec![
+            en_subject(actor),
+            plain(format!(" {} up.", en_verb(actor, "wake"))),
+        ],
+        LogEvent::UseItem { user, item } => vec![
+            en_subject(user),
+            plain(format!(" {} ", en_verb(user, "use"))),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::Identify { unknown, item } => vec![
+            name(unknown),
+            plain(" turns out to be "),
+            name(item),
+            plain("."),
+        ],
+        LogEvent::NothingToIdentify => vec![plain("There is nothing to identify.")],
+        LogEvent::NoCharges { item } => vec![name(item), plain(" has no charges left.")],
+        LogEvent::Recharge { item } => vec![name(item), plain(" hums with new power.")],
+        LogEvent::NothingToRecharge => vec![plain("There is nothing to recharge.")],
+        LogEvent::Cursed { item } => vec![
+            name(item),
+            TextFragment::new(" is cursed!").color(DAMAGE_COLOR),
+        ],
+        LogEvent::Uncurse { item } => vec![name(item), plain(" is no longer cursed.")],
+        LogEvent::NothingToUncurse => vec![plain("Nothing is cursed.")],
+        LogEvent::TooHeavy { item } => vec![name(item), plain(" is too heavy to carry.")],
+        LogEvent::Burdened => vec![
+            plain("You are now "),
+            TextFragment::new(ui_text(UiText::Burdened, Locale::En)).color(STATUS_COLOR),
+            plain("."),
+        ],
+        LogEvent::PickUpGold { amount } => vec![plain(format!("You pick up {} gold.", amount))],
+        LogEvent::Buy { item, price } => vec![
+            plain("You buy "),
+            name(item),
+            plain(format!(" for {} gold.", price)),
+        ],
+        LogEvent::Sell { item, price } => vec![
+            plain("You sell "),
+            name(item),
+            plain(format!(" for {} gold.", price)),
+        ],
+        LogEvent::NotEnoughGold { item } => {
+            vec![plain("You cannot afford "), name(item), plain(".")]
+        }
+        LogEvent::SellEquipped { item } => vec![
+            plain("You have to take off "),
+            name(item),
+            plain(" before selling it."),
+        ],
+        LogEvent::Cast { caster, ability } => vec![
+            en_subject(caster),
+            plain(format!(" {} ", en_verb(caster, "use"))),
+            name(ability),
+            plain("!"),
+        ],
+    }
+}
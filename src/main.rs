@@ -7,6 +7,7 @@ use ggez::{conf, graphics, Context, ContextBuilder, GameResult};
 use specs::prelude::*;
 use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
 
+use std::cmp::{max, min};
 use std::collections::HashMap;
 use std::env;
 use std::path;
@@ -16,18 +17,32 @@ use serde::{Deserialize, Serialize};
 mod inventory_system;
 mod spawner;
 
+mod keymap;
+pub use keymap::*;
+
 mod gamelog;
 pub use gamelog::*;
 
+mod faction;
+pub use faction::*;
+
+mod locale;
+pub use locale::*;
+
 mod player;
 pub use player::*;
 
 mod monster_ai_system;
 pub use monster_ai_system::*;
 
+mod monster_item_system;
+pub use monster_item_system::*;
+
 mod map;
 pub use map::*;
 
+mod pathing;
+
 mod component;
 pub use component::*;
 
@@ -37,21 +52,41 @@ pub use visibility_system::*;
 mod map_indexing_system;
 pub use map_indexing_system::*;
 
+mod combat;
+pub use combat::*;
+
+mod encumbrance;
+pub use encumbrance::*;
+
+mod trade;
+pub use trade::*;
+
 mod melee_combat_system;
 pub use melee_combat_system::*;
 
+mod ranged_combat_system;
+
 mod damage_system;
 pub use damage_system::*;
 
 mod turnhealing_system;
 pub use turnhealing_system::*;
 
+mod hunger_system;
+pub use hunger_system::*;
+
+mod identification;
+pub use identification::*;
+
 mod saveload_system;
 
 mod imgui_helper;
 mod ui_helper;
 
 mod random_table;
+
+#[cfg(test)]
+mod benchmark;
 use bracket_lib::prelude::*;
 
 const WINDOWSIZE_WIDTH: i32 = 40;
@@ -60,6 +95,9 @@ const PLAYER_WINDOW_WIDTH: i32 = 29;
 const PLAYER_WINDOW_HEIGHT: i32 = 19;
 
 const TILESIZE: i32 = 32;
+const INVENTORY_PAGE_SIZE: i32 = 26;
+const MESSAGE_LOG_SCROLL_STEP: f32 = 48.0;
+const STAIRS_NOISE_RADIUS: i32 = 8;
 
 #[derive(PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum GameImage {
@@ -73,6 +111,12 @@ pub enum GameImage {
     DownStairs,
     Sword,
     Shield,
+    Bow,
+    Ammo,
+    Food,
+    Wand,
+    Gold,
+    Merchant,
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -93,9 +137,34 @@ pub enum RunState {
     EndTurn,
     ShowInventory,
     ShowDropItem,
-    ShowTargeting { range: i32, item: Entity },
+    ShowThrowItem,
+    ShowDropCount {
+        item: Entity,
+        count: i32,
+        max: i32,
+    },
+    ShowTargeting {
+        range: i32,
+        item: Entity,
+        action: TargetAction,
+    },
+    ShowLook,
+    ShowMessageLog,
+    ShowTrade {
+        merchant: Entity,
+    },
     SaveGame,
-    MainMenu { state: MainMenuState },
+    MainMenu {
+        state: MainMenuState,
+    },
+}
+
+// What happens to the item once a target is chosen.
+#[derive(PartialEq, Copy, Clone)]
+pub enum TargetAction {
+    Use,
+    Fire,
+    Throw,
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -118,6 +187,74 @@ fn map_to_world(x: i32, y: i32) -> Point2<f32> {
     }
 }
 
+// Every component the game stores, plus the marker allocator saving needs.
+fn register_components(ecs: &mut World) {
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+
+    ecs.register::<Player>();
+    ecs.register::<Viewshed>();
+    ecs.register::<Monster>();
+    ecs.register::<SeenPlayer>();
+    ecs.register::<MonsterAiState>();
+    ecs.register::<Morale>();
+    ecs.register::<Faction>();
+    ecs.register::<Stealth>();
+    ecs.register::<Group>();
+    ecs.register::<LootTable>();
+    ecs.register::<Abilities>();
+
+    ecs.register::<Name>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<CombatStats>();
+    ecs.register::<DamageDice>();
+    ecs.register::<Resistance>();
+    ecs.register::<Vulnerability>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<DurationTurnHeal>();
+    ecs.register::<HungerClock>();
+    ecs.register::<ProvidesFood>();
+    ecs.register::<Identifies>();
+
+    ecs.register::<InBackpack>();
+    ecs.register::<InventorySlot>();
+    ecs.register::<Item>();
+    ecs.register::<Potion>();
+
+    ecs.register::<Consumable>();
+    ecs.register::<Quantity>();
+    ecs.register::<Charges>();
+    ecs.register::<Recharges>();
+    ecs.register::<ProvidesHealing>();
+    ecs.register::<Ranged>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<Paralyze>();
+
+    ecs.register::<DropItem>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<WantsToUseItem>();
+
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<Enchantment>();
+    ecs.register::<Cursed>();
+    ecs.register::<RemovesCurse>();
+    ecs.register::<Weight>();
+    ecs.register::<Value>();
+    ecs.register::<Gold>();
+    ecs.register::<Purse>();
+    ecs.register::<Merchant>();
+    ecs.register::<Launcher>();
+    ecs.register::<Ammunition>();
+    ecs.register::<WantsToThrow>();
+
+    ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+}
+
 fn main() -> GameResult {
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
@@ -160,6 +297,10 @@ pub struct State {
 
     hidpi_factor: f32,
     render_mode: RenderMode,
+    keymap: KeyMap,
+
+    target_cursor: Option<Point>,
+    last_target: Option<Entity>,
 
     pub ecs: World,
 }
@@ -202,13 +343,11 @@ impl State {
         {
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
             current_depth = worldmap_resource.depth;
-            worldmap = Map::new_map_rooms_and_corridors(current_depth + 1);
+            worldmap = Map::new_level(current_depth + 1);
             *worldmap_resource = worldmap.clone();
         }
 
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, current_depth + 1);
-        }
+        spawner::spawn_level(&mut self.ecs, &worldmap);
 
         let (player_x, player_y) = worldmap.rooms[0].center();
         let mut player_position = self.ecs.write_resource::<Point>();
@@ -227,8 +366,13 @@ impl State {
             vs.dirty = true
         }
 
+        // Stomping down the stairs alerts the new level.
+        let mut noises = self.ecs.fetch_mut::<Noises>();
+        noises.sounds.clear();
+        noises.emit(Point::new(player_x, player_y), STAIRS_NOISE_RADIUS);
+
         let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
-        gamelog.entries.push(GameLog::goto_next_level_log());
+        gamelog.push(LogEvent::Descend);
     }
 
     fn run_systems(&mut self) {
@@ -236,16 +380,21 @@ impl State {
         mapindex.run_now(&self.ecs);
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+        let mut mob_items = MonsterItemSystem {};
+        mob_items.run_now(&self.ecs);
+        let mut mob = MonsterAI {};
+        mob.run_now(&self.ecs);
         let mut pickup = inventory_system::ItemCollectionSystem {};
         pickup.run_now(&self.ecs);
         let mut drop = inventory_system::ItemDropSystem {};
         drop.run_now(&self.ecs);
         let mut potions = inventory_system::ItemUseSystem {};
         potions.run_now(&self.ecs);
-        let mut mob = MonsterAI {};
-        mob.run_now(&self.ecs);
+        ranged_combat_system::resolve_missiles(&mut self.ecs);
         let mut melee_combat = MeleeCombatSystem {};
         melee_combat.run_now(&self.ecs);
+        let mut hunger = HungerSystem {};
+        hunger.run_now(&self.ecs);
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
         damage_system::delete_the_dead(&mut self.ecs);
@@ -279,6 +428,24 @@ impl State {
             GameImage::Shield => {
                 graphics::TextFragment::new("盾").color(graphics::Color::new(0.0, 0.0, 0.8, 1.0))
             }
+            GameImage::Bow => {
+                graphics::TextFragment::new("弓").color(graphics::Color::new(0.6, 0.4, 0.2, 1.0))
+            }
+            GameImage::Ammo => {
+                graphics::TextFragment::new("矢").color(graphics::Color::new(0.8, 0.8, 0.8, 1.0))
+            }
+            GameImage::Food => {
+                graphics::TextFragment::new("食").color(graphics::Color::new(0.9, 0.6, 0.3, 1.0))
+            }
+            GameImage::Wand => {
+                graphics::TextFragment::new("杖").color(graphics::Color::new(0.7, 0.3, 1.0, 1.0))
+            }
+            GameImage::Gold => {
+                graphics::TextFragment::new("金").color(graphics::Color::new(1.0, 0.85, 0.0, 1.0))
+            }
+            GameImage::Merchant => {
+                graphics::TextFragment::new("商").color(graphics::Color::new(0.0, 1.0, 1.0, 1.0))
+            }
             _ => graphics::TextFragment::new("謎").color(graphics::Color::new(1.0, 1.0, 1.0, 1.0)),
         }
     }
@@ -331,69 +498,38 @@ impl State {
 
         // Create State
         let mut gs = State {
-            font: font,
+            font,
             mouse_x: 0.0,
             mouse_y: 0.0,
 
             images: prepare_images,
             imgui: imgui_helper::ImGuiWrapper::new(ctx),
-            hidpi_factor: hidpi_factor,
+            hidpi_factor,
 
             render_mode: RenderMode::Tile,
+            keymap: KeyMap::load(),
+
+            target_cursor: None,
+            last_target: None,
 
             ecs: World::new(),
         };
 
-        //
-        // gs.ecs.register
-        //
-
-        gs.ecs.register::<SimpleMarker<SerializeMe>>();
-        gs.ecs.register::<SerializationHelper>();
-        gs.ecs.register::<Position>();
-        gs.ecs.register::<Renderable>();
-
-        gs.ecs.register::<Player>();
-        gs.ecs.register::<Viewshed>();
-        gs.ecs.register::<Monster>();
-        gs.ecs.register::<SeenPlayer>();
-
-        gs.ecs.register::<Name>();
-        gs.ecs.register::<BlocksTile>();
-        gs.ecs.register::<CombatStats>();
-        gs.ecs.register::<WantsToMelee>();
-        gs.ecs.register::<SufferDamage>();
-        gs.ecs.register::<DurationTurnHeal>();
-
-        gs.ecs.register::<InBackpack>();
-        gs.ecs.register::<Item>();
-        gs.ecs.register::<Potion>();
-
-        gs.ecs.register::<Consumable>();
-        gs.ecs.register::<ProvidesHealing>();
-        gs.ecs.register::<Ranged>();
-        gs.ecs.register::<InflictsDamage>();
-        gs.ecs.register::<AreaOfEffect>();
-        gs.ecs.register::<Paralyze>();
-
-        gs.ecs.register::<DropItem>();
-        gs.ecs.register::<WantsToPickupItem>();
-        gs.ecs.register::<WantsToUseItem>();
-
-        gs.ecs.register::<Equippable>();
+        register_components(&mut gs.ecs);
 
-        gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
-
-        let map: Map = Map::new_map_rooms_and_corridors(1);
+        let map: Map = Map::new_level(1);
         let (player_x, player_y) = map.rooms[0].center();
 
-        gs.ecs.insert(RandomNumberGenerator::new());
+        let mut rng = RandomNumberGenerator::new();
+        gs.ecs.insert(Identification::new(&mut rng));
+        gs.ecs.insert(rng);
+        gs.ecs.insert(spawner::SpawnRecord::default());
+        gs.ecs.insert(Noises::new());
+        gs.ecs.insert(FactionTable::load());
 
         let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
 
-        for room in map.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut gs.ecs, room, 1);
-        }
+        spawner::spawn_level(&mut gs.ecs, &map);
 
         gs.ecs.insert(map);
 
@@ -402,14 +538,11 @@ impl State {
         gs.ecs.insert(RunState::MainMenu {
             state: MainMenuState::Waiting,
         });
-        gs.ecs.insert(gamelog::GameLog {
-            entries: vec![vec![
-                graphics::TextFragment::new("Sushyの世界")
-                    .color(graphics::Color::new(1.0, 1.0, 0.0, 1.0)),
-                graphics::TextFragment::new("へようこそ(Welcome to Sushy World)"),
-            ]],
-            font: graphics::Font::new(ctx, "/PixelMplus.ttf").unwrap(),
-        });
+        let mut gamelog =
+            gamelog::GameLog::new(graphics::Font::new(ctx, "/PixelMplus.ttf").unwrap());
+        gamelog.push(LogEvent::Welcome);
+        gs.ecs.insert(gamelog);
+        gs.ecs.insert(Locale::Ja);
         Ok(gs)
     }
 
@@ -443,7 +576,6 @@ impl State {
                             )
                             .expect("cannot draw wall");
                         } else {
-                            let use_text;
                             match tile.background {
                                 None => {}
                                 Some(color) => {
@@ -468,20 +600,14 @@ impl State {
                                 }
                             }
 
-                            match tile.tiletype {
-                                TileType::Floor => {
-                                    use_text = graphics::TextFragment::new("床")
-                                        .color(graphics::Color::new(0.1, 0.1, 0.1, 0.4));
-                                }
-                                TileType::Wall => {
-                                    use_text = graphics::TextFragment::new("壁")
-                                        .color(graphics::Color::new(0.2, 0.0, 0.0, 1.0));
-                                }
-                                TileType::DownStairs => {
-                                    use_text = graphics::TextFragment::new("門")
-                                        .color(graphics::Color::new(0.5, 0.5, 0.5, 1.0));
-                                }
-                            }
+                            let use_text = match tile.tiletype {
+                                TileType::Floor => graphics::TextFragment::new("床")
+                                    .color(graphics::Color::new(0.1, 0.1, 0.1, 0.4)),
+                                TileType::Wall => graphics::TextFragment::new("壁")
+                                    .color(graphics::Color::new(0.2, 0.0, 0.0, 1.0)),
+                                TileType::DownStairs => graphics::TextFragment::new("門")
+                                    .color(graphics::Color::new(0.5, 0.5, 0.5, 1.0)),
+                            };
 
                             ui_helper::draw_tile_text(ctx, use_text, x, y, self.font);
                         }
@@ -512,12 +638,11 @@ impl State {
                                 );
 
                             if distance <= 2.0 {
-                                let use_alpha;
-                                if distance < 1.5 {
-                                    use_alpha = 0.002;
+                                let use_alpha = if distance < 1.5 {
+                                    0.002
                                 } else {
-                                    use_alpha = 0.401 - (distance * 0.2);
-                                }
+                                    0.401 - (distance * 0.2)
+                                };
                                 let mask_rect = graphics::Mesh::new_rectangle(
                                     ctx,
                                     graphics::DrawMode::fill(),
@@ -557,6 +682,69 @@ impl State {
         }
     }
 
+    fn target_cursor(&mut self, range: i32) -> Point {
+        match self.target_cursor {
+            Some(cursor) => cursor,
+            None => {
+                let cursor = ui_helper::first_target(&self.ecs, range, self.last_target);
+                self.target_cursor = Some(cursor);
+                cursor
+            }
+        }
+    }
+
+    fn leave_cursor_modes(&mut self, runstate: RunState) {
+        match runstate {
+            RunState::ShowTargeting { .. } => {}
+            _ => self.target_cursor = None,
+        }
+        if runstate != RunState::ShowLook {
+            self.imgui.look_point = None;
+        }
+    }
+
+    fn look_range(&self) -> i32 {
+        let player_entity = self.ecs.fetch::<Entity>();
+        let viewsheds = self.ecs.read_storage::<Viewshed>();
+        match viewsheds.get(*player_entity) {
+            Some(viewshed) => viewshed.range,
+            None => 0,
+        }
+    }
+
+    fn select_inventory_item(
+        &mut self,
+        keycode: KeyCode,
+        keymod: KeyMods,
+        command: Option<Command>,
+        runstate: RunState,
+        action: fn(&mut World, i32, &mut bool) -> RunState,
+    ) -> RunState {
+        // Letters win over the keymap so that vi-keys can still pick items.
+        if keymod == KeyMods::NONE {
+            if let Some(letter) = keycode_to_letter(keycode) {
+                let slot = self.imgui.inventory_page * INVENTORY_PAGE_SIZE + letter;
+                return action(&mut self.ecs, slot, &mut self.imgui.inventory_window_show);
+            }
+        }
+        match command {
+            Some(Command::Cancel) => {
+                self.imgui.inventory_window_show = false;
+                RunState::AwaitingInput
+            }
+            Some(Command::MoveLeft) => {
+                self.imgui.inventory_page = max(0, self.imgui.inventory_page - 1);
+                runstate
+            }
+            Some(Command::MoveRight) => {
+                let last_page = inventory_page_count(&self.ecs) - 1;
+                self.imgui.inventory_page = min(last_page, self.imgui.inventory_page + 1);
+                runstate
+            }
+            _ => runstate,
+        }
+    }
+
     fn draw_title(&mut self, ctx: &mut Context) {
         self.imgui.render(ctx, &mut self.ecs, self.hidpi_factor);
     }
@@ -564,20 +752,21 @@ impl State {
     fn draw_maingame(&mut self, ctx: &mut Context, runstatus: RunState) {
         self.draw_map(ctx);
 
-        if let RunState::ShowTargeting { range, item } = runstatus {
+        if let RunState::ShowTargeting { range, item, .. } = runstatus {
+            let cursor = self.target_cursor(range);
             ui_helper::draw_ranged_target(&self.ecs, ctx, range);
             let aoe = self.ecs.read_storage::<AreaOfEffect>();
             let is_aoe_item = aoe.get(item);
             match is_aoe_item {
                 None => {}
-                Some(aoe_item) => ui_helper::draw_aoe_radius(
-                    ctx,
-                    &self.ecs,
-                    self.mouse_x,
-                    self.mouse_y,
-                    aoe_item.radius,
-                ),
+                Some(aoe_item) => {
+                    ui_helper::draw_aoe_radius(ctx, &self.ecs, cursor, aoe_item.radius)
+                }
             }
+            ui_helper::draw_target_cursor(ctx, &self.ecs, cursor);
+        }
+        if let Some(look_point) = self.imgui.look_point {
+            ui_helper::draw_target_cursor(ctx, &self.ecs, look_point);
         }
         {
             // --------------------
@@ -586,12 +775,17 @@ impl State {
             let players = self.ecs.read_storage::<Player>();
             let renderables = self.ecs.read_storage::<Renderable>();
             let positions = self.ecs.read_storage::<Position>();
+            let ai_states = self.ecs.read_storage::<MonsterAiState>();
+            let names = self.ecs.read_storage::<Name>();
+            let identification = self.ecs.fetch::<Identification>();
             let map = self.ecs.fetch::<Map>();
 
-            let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-            data.sort_by(|&a, &b| a.1.render_layer.cmp(&b.1.render_layer));
+            let mut data = (&positions, &renderables, ai_states.maybe(), names.maybe())
+                .join()
+                .collect::<Vec<_>>();
+            data.sort_by_key(|&a| a.1.render_layer);
             for (_player, player_pos) in (&players, &positions).join() {
-                for (pos, render) in data.iter() {
+                for (pos, render, ai, name) in data.iter() {
                     if pos.x < player_pos.to_left()
                         || pos.y < player_pos.to_top()
                         || pos.x > player_pos.to_right()
@@ -610,41 +804,55 @@ impl State {
                     if map.visible_tiles[idx] {
                         if ui_helper::p_to_map(self.mouse_x) + player_pos.to_left() >= 0
                             && ui_helper::p_to_map(self.mouse_y) + player_pos.to_top() >= 0
+                            && idx == map.xy_idx_from_mouse(self.mouse_x, self.mouse_y, *player_pos)
                         {
-                            if idx == map.xy_idx_from_mouse(self.mouse_x, self.mouse_y, *player_pos)
-                            {
-                                ui_helper::draw_object_focus_rect(
-                                    ctx,
-                                    &self.ecs,
-                                    self.mouse_x,
-                                    self.mouse_y,
-                                    *pos,
-                                );
-                                ui_helper::draw_tooltip_with_mouse_motion(
-                                    ctx,
-                                    &self.ecs,
-                                    self.mouse_x,
-                                    self.mouse_y,
-                                    self.font,
-                                );
-                            }
+                            ui_helper::draw_object_focus_rect(
+                                ctx,
+                                &self.ecs,
+                                self.mouse_x,
+                                self.mouse_y,
+                                pos,
+                            );
+                            ui_helper::draw_tooltip_with_mouse_motion(
+                                ctx,
+                                &self.ecs,
+                                self.mouse_x,
+                                self.mouse_y,
+                                self.font,
+                            );
                         }
-                        if self.render_mode == RenderMode::Tile {
+                        // Potions and scrolls take this run's look for their type.
+                        let look = name.and_then(|name| identification.appearance(&name.name));
+                        // Objects without a sprite yet are drawn as text in tile mode too.
+                        let image = match self.render_mode {
+                            RenderMode::Tile => self.images.get(&render.image),
+                            RenderMode::Unicode => None,
+                        };
+                        if let Some(image) = image {
+                            let tint = look.map_or(graphics::WHITE, |look| look.color());
                             graphics::draw(
                                 ctx,
-                                self.images.get(&render.image).unwrap(),
-                                graphics::DrawParam::new().dest(draw_position),
+                                image,
+                                graphics::DrawParam::new().dest(draw_position).color(tint),
                             )
                             .unwrap();
                         } else {
                             ui_helper::draw_tile_text(
                                 ctx,
-                                self.enum_to_unicode(&render),
+                                look.map_or_else(
+                                    || self.enum_to_unicode(render),
+                                    |look| look.text_fragment(),
+                                ),
                                 pos.x - (player_pos.to_left() + 1),
                                 pos.y - (player_pos.to_top() + 1),
                                 self.font,
                             );
                         }
+                        if let Some(ai) = ai {
+                            if ai.state == AiState::Sleeping {
+                                ui_helper::draw_sleep_marker(ctx, draw_position, self.font);
+                            }
+                        }
                     }
                 }
             }
@@ -657,7 +865,7 @@ impl State {
 }
 
 impl EventHandler for State {
-    fn quit_event(&mut self, ctx: &mut Context) -> bool {
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
         saveload_system::save_game(&mut self.ecs);
         false
     }
@@ -669,93 +877,196 @@ impl EventHandler for State {
         keymod: KeyMods,
         _repeat: bool,
     ) {
+        self.imgui.update_key(keycode, true);
+        if self.imgui.want_capture_keyboard() {
+            return;
+        }
+
         let mut newrunstate;
         {
             let runstate = self.ecs.fetch::<RunState>();
             newrunstate = *runstate;
         }
+        let command = self.keymap.lookup(keycode, keymod);
         match newrunstate {
             RunState::ShowInventory => {
-                match keymod {
-                    KeyMods::NONE => {
-                        match keycode {
-                            KeyCode::Escape => {
-                                self.imgui.inventory_window_show = false;
-                                newrunstate = RunState::AwaitingInput
-                            }
-                            _ => {
-                                // keycode  "a" -> 10
-                                newrunstate = try_use_item(
-                                    &mut self.ecs,
-                                    (keycode as i32) - 10,
-                                    &mut self.imgui.inventory_window_show,
-                                );
+                newrunstate =
+                    self.select_inventory_item(keycode, keymod, command, newrunstate, try_use_item);
+            }
+            RunState::ShowDropItem => {
+                newrunstate = self.select_inventory_item(
+                    keycode,
+                    keymod,
+                    command,
+                    newrunstate,
+                    try_drop_item,
+                );
+            }
+            RunState::ShowThrowItem => {
+                newrunstate = self.select_inventory_item(
+                    keycode,
+                    keymod,
+                    command,
+                    newrunstate,
+                    try_throw_item,
+                );
+            }
+            RunState::ShowDropCount {
+                item,
+                count,
+                max: most,
+            } => match command {
+                Some(Command::Cancel) => newrunstate = RunState::AwaitingInput,
+                Some(Command::Confirm) => newrunstate = drop_count(&mut self.ecs, item, count),
+                Some(Command::MoveLeft) | Some(Command::MoveDown) => {
+                    newrunstate = RunState::ShowDropCount {
+                        item,
+                        count: max(1, count - 1),
+                        max: most,
+                    }
+                }
+                Some(Command::MoveRight) | Some(Command::MoveUp) => {
+                    newrunstate = RunState::ShowDropCount {
+                        item,
+                        count: min(most, count + 1),
+                        max: most,
+                    }
+                }
+                _ => {}
+            },
+            RunState::ShowTargeting {
+                range,
+                item,
+                action,
+            } => {
+                let cursor = self.target_cursor(range);
+                match command {
+                    Some(Command::Cancel) => newrunstate = RunState::AwaitingInput,
+                    Some(Command::NextTarget) => {
+                        self.target_cursor =
+                            Some(ui_helper::cycle_target(&self.ecs, range, cursor));
+                    }
+                    Some(Command::Confirm) => {
+                        let result =
+                            ui_helper::try_target_point(&self.ecs, cursor, item, range, action);
+                        if result == RunState::PlayerTurn {
+                            self.last_target = ui_helper::monster_at(&self.ecs, cursor);
+                            newrunstate = RunState::PlayerTurn;
+                        }
+                    }
+                    Some(command) => {
+                        // The cursor stops at the edge of what can be targeted,
+                        // which also keeps it on the map.
+                        if let Some((delta_x, delta_y)) = command.move_delta() {
+                            let moved = Point::new(cursor.x + delta_x, cursor.y + delta_y);
+                            if ui_helper::inside_range(&self.ecs, range).contains(&moved) {
+                                self.target_cursor = Some(moved);
                             }
                         }
                     }
-                    _ => {}
+                    None => {}
                 }
             }
-            RunState::ShowDropItem => {
-                match keymod {
-                    KeyMods::NONE => {
-                        match keycode {
-                            KeyCode::Escape => {
-                                self.imgui.inventory_window_show = false;
-                                newrunstate = RunState::AwaitingInput
-                            }
-                            _ => {
-                                // keycode  "a" -> 10
-                                newrunstate = try_drop_item(
-                                    &mut self.ecs,
-                                    (keycode as i32) - 10,
-                                    &mut self.imgui.inventory_window_show,
-                                );
-                            }
+            RunState::ShowLook => {
+                let look_point = match self.imgui.look_point {
+                    Some(look_point) => look_point,
+                    None => *self.ecs.fetch::<Point>(),
+                };
+                match command {
+                    Some(Command::Cancel) | Some(Command::Look) => {
+                        newrunstate = RunState::AwaitingInput
+                    }
+                    Some(Command::NextTarget) => {
+                        let range = self.look_range();
+                        self.imgui.look_point =
+                            Some(ui_helper::cycle_target(&self.ecs, range, look_point));
+                    }
+                    Some(command) => {
+                        if let Some((delta_x, delta_y)) = command.move_delta() {
+                            self.imgui.look_point =
+                                Some(Point::new(look_point.x + delta_x, look_point.y + delta_y));
                         }
                     }
-                    _ => {}
+                    None => {}
                 }
             }
-            RunState::ShowTargeting { range: _, item: _ } => match keymod {
-                KeyMods::NONE => match keycode {
-                    KeyCode::Escape => newrunstate = RunState::AwaitingInput,
-                    _ => {}
-                },
+            RunState::ShowTrade { merchant } => {
+                // A letter buys from the merchant's list, Shift and a letter
+                // sells from the player's. Both lists turn pages together.
+                let page_start = self.imgui.inventory_page * INVENTORY_PAGE_SIZE;
+                match keycode_to_letter(keycode) {
+                    Some(letter) if keymod == KeyMods::NONE => {
+                        newrunstate = try_buy(&mut self.ecs, merchant, page_start + letter)
+                    }
+                    Some(letter) if keymod == KeyMods::SHIFT => {
+                        newrunstate = try_sell(&mut self.ecs, merchant, page_start + letter)
+                    }
+                    _ => match command {
+                        Some(Command::Cancel) => newrunstate = RunState::AwaitingInput,
+                        Some(Command::MoveLeft) => {
+                            self.imgui.inventory_page = max(0, self.imgui.inventory_page - 1);
+                        }
+                        Some(Command::MoveRight) => {
+                            let last_page = trade_page_count(&self.ecs, merchant) - 1;
+                            self.imgui.inventory_page =
+                                min(last_page, self.imgui.inventory_page + 1);
+                        }
+                        _ => {}
+                    },
+                }
+            }
+            RunState::ShowMessageLog => match command {
+                Some(Command::Cancel) | Some(Command::MessageLog) => {
+                    self.imgui.message_log_show = false;
+                    newrunstate = RunState::AwaitingInput;
+                }
+                Some(Command::MoveUp) => self.imgui.message_log_scroll -= MESSAGE_LOG_SCROLL_STEP,
+                Some(Command::MoveDown) => self.imgui.message_log_scroll += MESSAGE_LOG_SCROLL_STEP,
                 _ => {}
             },
-            RunState::AwaitingInput => match keymod {
-                KeyMods::SHIFT => match keycode {
-                    KeyCode::Left => newrunstate = try_move_player(-1, -1, &mut self.ecs),
-                    KeyCode::Right => newrunstate = try_move_player(1, -1, &mut self.ecs),
-                    KeyCode::Period => newrunstate = try_next_level(&mut self.ecs),
-                    _ => {
-                        return;
-                    }
-                },
-                KeyMods::CTRL => match keycode {
-                    KeyCode::Left => newrunstate = try_move_player(-1, 1, &mut self.ecs),
-                    KeyCode::Right => newrunstate = try_move_player(1, 1, &mut self.ecs),
-                    _ => {
-                        return;
+            RunState::AwaitingInput => match command {
+                None => {
+                    return;
+                }
+                Some(command) => match command {
+                    _ if command.move_delta().is_some() => {
+                        let (delta_x, delta_y) = command.move_delta().unwrap();
+                        newrunstate = try_move_player(delta_x, delta_y, &mut self.ecs);
+                        if let RunState::ShowTrade { .. } = newrunstate {
+                            self.imgui.inventory_page = 0;
+                        }
                     }
-                },
-                KeyMods::NONE => match keycode {
-                    KeyCode::Left => newrunstate = try_move_player(-1, 0, &mut self.ecs),
-                    KeyCode::Right => newrunstate = try_move_player(1, 0, &mut self.ecs),
-                    KeyCode::Up => newrunstate = try_move_player(0, -1, &mut self.ecs),
-                    KeyCode::Down => newrunstate = try_move_player(0, 1, &mut self.ecs),
-                    KeyCode::D => {
+                    Command::DownStairs => newrunstate = try_next_level(&mut self.ecs),
+                    Command::Drop => {
                         self.imgui.inventory_window_show = true;
+                        self.imgui.inventory_page = 0;
                         newrunstate = RunState::ShowDropItem;
                     }
-                    KeyCode::G => newrunstate = get_item(&mut self.ecs),
-                    KeyCode::I => {
+                    Command::PickUp => newrunstate = get_item(&mut self.ecs),
+                    Command::Fire => newrunstate = try_fire(&mut self.ecs),
+                    Command::Throw => {
                         self.imgui.inventory_window_show = true;
+                        self.imgui.inventory_page = 0;
+                        newrunstate = RunState::ShowThrowItem;
+                    }
+                    Command::Inventory => {
+                        self.imgui.inventory_window_show = true;
+                        self.imgui.inventory_page = 0;
                         newrunstate = RunState::ShowInventory;
                     }
-                    KeyCode::Comma => newrunstate = RunState::PlayerTurn,
-                    KeyCode::F11 => {
+                    Command::Wait => newrunstate = RunState::PlayerTurn,
+                    Command::Options => {
+                        self.imgui.options_window_show = !self.imgui.options_window_show;
+                    }
+                    Command::MessageLog => {
+                        self.imgui.message_log_show = true;
+                        newrunstate = RunState::ShowMessageLog;
+                    }
+                    Command::Look => {
+                        self.imgui.look_point = Some(*self.ecs.fetch::<Point>());
+                        newrunstate = RunState::ShowLook;
+                    }
+                    Command::ToggleRenderMode => {
                         if self.render_mode == RenderMode::Tile {
                             self.render_mode = RenderMode::Unicode;
                         } else {
@@ -764,12 +1075,10 @@ impl EventHandler for State {
                     }
                     _ => {}
                 },
-                _ => {
-                    return;
-                }
             },
             _ => {}
         }
+        self.leave_cursor_modes(newrunstate);
 
         {
             let mut runwriter = self.ecs.write_resource::<RunState>();
@@ -777,13 +1086,42 @@ impl EventHandler for State {
         }
     }
 
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
+        self.imgui.update_key(keycode, false);
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        self.imgui.update_text(character);
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        self.imgui.update_mouse_wheel(y);
+    }
+
     fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _xrel: f32, _yrel: f32) {
         self.mouse_x = x;
         self.mouse_y = y;
         self.imgui.update_mouse_pos(x, y);
+
+        let runstate = *self.ecs.fetch::<RunState>();
+        match runstate {
+            RunState::ShowTargeting { .. } => {
+                self.target_cursor = Some(ui_helper::mouse_to_map_point(&self.ecs, x, y));
+            }
+            RunState::ShowLook => {
+                self.imgui.look_point = Some(ui_helper::mouse_to_map_point(&self.ecs, x, y));
+            }
+            _ => {}
+        }
     }
 
-    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
         self.imgui.update_mouse_down((false, false, false));
     }
 
@@ -796,12 +1134,26 @@ impl EventHandler for State {
         let mut get_another_event = false;
 
         match newrunstate {
-            RunState::ShowTargeting { range, item } => {
-                newrunstate = ui_helper::try_target_object(&self.ecs, x, y, item, range);
+            RunState::ShowTargeting {
+                range,
+                item,
+                action,
+            } => {
+                let target = ui_helper::mouse_to_map_point(&self.ecs, x, y);
+                newrunstate = ui_helper::try_target_point(&self.ecs, target, item, range, action);
+                if newrunstate == RunState::PlayerTurn {
+                    self.last_target = ui_helper::monster_at(&self.ecs, target);
+                }
+                get_another_event = true;
+            }
+            RunState::AwaitingInput if button == MouseButton::Right => {
+                self.imgui.look_point = Some(ui_helper::mouse_to_map_point(&self.ecs, x, y));
+                newrunstate = RunState::ShowLook;
                 get_another_event = true;
             }
             _ => {}
         }
+        self.leave_cursor_modes(newrunstate);
 
         if !get_another_event {
             self.imgui.update_mouse_down((
@@ -817,7 +1169,7 @@ impl EventHandler for State {
         }
     }
 
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
+    fn update(&mut self, _ctx: &mut Context) -> GameResult {
         let mut newrunstate;
         {
             let runstate = self.ecs.fetch::<RunState>();
@@ -870,7 +1222,12 @@ impl EventHandler for State {
             }
             RunState::ShowInventory => {}
             RunState::ShowDropItem => {}
-            RunState::ShowTargeting { range, item } => {}
+            RunState::ShowThrowItem => {}
+            RunState::ShowDropCount { .. } => {}
+            RunState::ShowLook => {}
+            RunState::ShowMessageLog => {}
+            RunState::ShowTrade { .. } => {}
+            RunState::ShowTargeting { .. } => {}
         }
 
         {
@@ -885,7 +1242,7 @@ impl EventHandler for State {
         graphics::clear(ctx, graphics::BLACK);
         let runstatus = *(self.ecs.fetch::<RunState>()).clone();
         match runstatus {
-            RunState::MainMenu { state } => {
+            RunState::MainMenu { state: _ } => {
                 self.draw_title(ctx);
             }
             _ => {
@@ -1,4 +1,4 @@
-use super::{ui_helper, GameImage, Position, TILESIZE};
+use super::{ui_helper, GameImage, Position};
 use bracket_lib::prelude::*;
 use ggez::graphics::Color;
 use serde::{Deserialize, Serialize};
@@ -10,6 +10,10 @@ pub const MAPSIZE_HEIGHT: usize = 64;
 pub const MAPSIZE_COUNT: usize = MAPSIZE_HEIGHT * MAPSIZE_WIDTH;
 pub const MIN_ROOMS: i32 = 10;
 pub const TRY_ROOMS: i32 = 3;
+// Every this many levels the stairs lead down into a town instead of a dungeon.
+pub const TOWN_EVERY: i32 = 5;
+// One dungeon level in this many has a shop in one of its rooms.
+const SHOP_CHANCE: i32 = 4;
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
@@ -65,6 +69,9 @@ pub struct Map {
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
     pub rooms: Vec<Rect>,
+    // Indices into `rooms` that hold a merchant instead of monsters.
+    pub shops: Vec<usize>,
+    pub town: bool,
 
     pub width: i32,
     pub height: i32,
@@ -125,14 +132,14 @@ impl Map {
             return false;
         }
         let idx = self.xy_idx(x, y);
-        !self.blocked[idx as usize]
+        !self.blocked[idx]
     }
 
     fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
         for x in min(x1, x2)..=max(x1, x2) {
             let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < (MAPSIZE_HEIGHT * MAPSIZE_WIDTH) as usize {
-                self.tiles[idx as usize].tiletype = TileType::Floor;
+            if idx > 0 && idx < (MAPSIZE_HEIGHT * MAPSIZE_WIDTH) {
+                self.tiles[idx].tiletype = TileType::Floor;
             }
         }
     }
@@ -140,9 +147,9 @@ impl Map {
     fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
         for y in min(y1, y2)..=max(y1, y2) {
             let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < (MAPSIZE_HEIGHT * MAPSIZE_WIDTH) as usize {
+            if idx > 0 && idx < (MAPSIZE_HEIGHT * MAPSIZE_WIDTH) {
                 let idx = self.xy_idx(x, y);
-                self.tiles[idx as usize].tiletype = TileType::Floor;
+                self.tiles[idx].tiletype = TileType::Floor;
             }
         }
     }
@@ -151,7 +158,7 @@ impl Map {
         for y in room.y1 + 1..=room.y2 {
             for x in room.x1 + 1..=room.x2 {
                 let idx = self.xy_idx(x, y);
-                self.tiles[idx as usize].tiletype = TileType::Floor;
+                self.tiles[idx].tiletype = TileType::Floor;
             }
         }
     }
@@ -198,6 +205,18 @@ impl Map {
         }
     }
 
+    // Monsters move one after another within a turn, so a mover claims its
+    // new cell here before MapIndexingSystem rebuilds `blocked`. Two movers
+    // can never end up on the same cell.
+    pub fn move_blocker(&mut self, from: usize, to: usize) -> bool {
+        if self.blocked[to] {
+            return false;
+        }
+        self.blocked[from] = false;
+        self.blocked[to] = true;
+        true
+    }
+
     pub fn clear_content_index(&mut self) {
         for content in self.tile_content.iter_mut() {
             content.clear();
@@ -211,29 +230,43 @@ impl Map {
         }
     }
 
-    pub fn new_map_rooms_and_corridors(depth: i32) -> Map {
-        const MAPSIZE_FOR_INIT: usize = (MAPSIZE_WIDTH * MAPSIZE_HEIGHT) as usize;
-        let mut map = Map {
-            visible_tiles: vec![false; (MAPSIZE_WIDTH * MAPSIZE_HEIGHT) as usize],
-            revealed_tiles: vec![false; (MAPSIZE_WIDTH * MAPSIZE_HEIGHT) as usize],
-            blocked: vec![false; (MAPSIZE_WIDTH * MAPSIZE_HEIGHT) as usize],
+    pub fn new_level(depth: i32) -> Map {
+        if depth % TOWN_EVERY == 0 {
+            Map::new_town(depth)
+        } else {
+            Map::new_map_rooms_and_corridors(depth)
+        }
+    }
+
+    fn new_solid(depth: i32) -> Map {
+        const MAPSIZE_FOR_INIT: usize = MAPSIZE_WIDTH * MAPSIZE_HEIGHT;
+        Map {
+            visible_tiles: vec![false; MAPSIZE_WIDTH * MAPSIZE_HEIGHT],
+            revealed_tiles: vec![false; MAPSIZE_WIDTH * MAPSIZE_HEIGHT],
+            blocked: vec![false; MAPSIZE_WIDTH * MAPSIZE_HEIGHT],
 
             tiles: vec![
                 Tile {
                     tiletype: TileType::Wall,
                     background: None
                 };
-                (MAPSIZE_WIDTH * MAPSIZE_HEIGHT) as usize
+                MAPSIZE_WIDTH * MAPSIZE_HEIGHT
             ],
             rooms: Vec::new(),
+            shops: Vec::new(),
+            town: false,
 
             width: MAPSIZE_WIDTH as i32,
             height: MAPSIZE_HEIGHT as i32,
 
-            depth: depth,
+            depth,
 
             tile_content: vec![Vec::new(); MAPSIZE_FOR_INIT],
-        };
+        }
+    }
+
+    pub fn new_map_rooms_and_corridors(depth: i32) -> Map {
+        let mut map = Map::new_solid(depth);
 
         while map.rooms.len() < MIN_ROOMS as usize {
             for _ in 0..TRY_ROOMS {
@@ -241,6 +274,13 @@ impl Map {
             }
         }
 
+        // Never the first room, where the player arrives, nor the last, which
+        // holds the stairs.
+        let mut rng = RandomNumberGenerator::new();
+        if rng.roll_dice(1, SHOP_CHANCE) == 1 {
+            map.shops.push(rng.range(1, map.rooms.len() - 1));
+        }
+
         map.set_background();
 
         let stairs_position = map.rooms[map.rooms.len() - 1].center();
@@ -248,11 +288,44 @@ impl Map {
         map.tiles[stairs_idx].tiletype = TileType::DownStairs;
         map
     }
+
+    // A square plaza with a shop off each side and the stairs in a corner.
+    // Nothing hostile lives here.
+    pub fn new_town(depth: i32) -> Map {
+        let mut map = Map::new_solid(depth);
+        map.town = true;
+
+        let plaza = Rect::new(20, 20, 24, 24);
+        map.apply_room_to_map(&plaza);
+        map.rooms.push(plaza.clone());
+        let (plaza_x, plaza_y) = plaza.center();
+
+        let shops = [
+            Rect::new(28, 6, 8, 6),
+            Rect::new(28, 52, 8, 6),
+            Rect::new(6, 28, 6, 8),
+            Rect::new(52, 28, 6, 8),
+        ];
+        for shop in shops.iter() {
+            map.apply_room_to_map(shop);
+            let (shop_x, shop_y) = shop.center();
+            map.apply_horizontal_tunnel(shop_x, plaza_x, shop_y);
+            map.apply_vertical_tunnel(shop_y, plaza_y, plaza_x);
+            map.shops.push(map.rooms.len());
+            map.rooms.push(shop.clone());
+        }
+
+        map.set_background();
+
+        let stairs_idx = map.xy_idx(plaza.x2 - 1, plaza.y2 - 1);
+        map.tiles[stairs_idx].tiletype = TileType::DownStairs;
+        map
+    }
 }
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize].tiletype == TileType::Wall
+        self.tiles[idx].tiletype == TileType::Wall
     }
 
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
@@ -1,34 +1,139 @@
-use super::{CombatStats, GameLog, Name, SufferDamage, WantsToMelee};
+use super::{
+    attack_roll, burden_penalty, carried_weight, damage_roll, gamelog::seen_by_player,
+    slot_enchantment, AttackRoll, CombatStats, DamageDice, DamageType, Dice, Enchantment,
+    EquipmentSlot, Equipped, GameLog, InBackpack, LogEvent, Map, Name, Noises, Noun, Position,
+    Quantity, SufferDamage, WantsToMelee, Weight,
+};
+use bracket_lib::prelude::{console, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+const MELEE_NOISE_RADIUS: i32 = 6;
+// Bare fists, for anyone without dice of their own.
+const UNARMED_DICE: Dice = Dice {
+    count: 1,
+    sides: 3,
+    bonus: 0,
+};
+
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, Noises>,
+        WriteExpect<'a, RandomNumberGenerator>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, DamageDice>,
+        ReadStorage<'a, Equipped>,
         WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Enchantment>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Quantity>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, combat_stats, mut inflict_damage) = data;
+        let (
+            entities,
+            player_entity,
+            map,
+            mut log,
+            mut noises,
+            mut rng,
+            mut wants_melee,
+            names,
+            combat_stats,
+            damage_dice,
+            equipped,
+            mut inflict_damage,
+            positions,
+            enchantments,
+            backpack,
+            weights,
+            quantities,
+        ) = data;
 
-        for (_entity, wants_melee, name, stats) in
+        for (entity, wants_melee, _name, stats) in
             (&entities, &wants_melee, &names, &combat_stats).join()
         {
             if stats.hp > 0 {
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if target_stats.hp > 0 {
-                    let target_name = names.get(wants_melee.target).unwrap();
+                    let attacker = Noun::of(&names, entity, *player_entity);
+                    let target = Noun::of(&names, wants_melee.target, *player_entity);
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    // A wielded weapon's dice replace the attacker's own.
+                    let weapon = (&equipped, &damage_dice)
+                        .join()
+                        .find(|(e, _)| e.owner == entity && e.slot == EquipmentSlot::Melee)
+                        .map(|(_, dice)| dice);
+                    let dice = match weapon.or_else(|| damage_dice.get(entity)) {
+                        None => UNARMED_DICE,
+                        Some(text) => Dice::parse(&text.dice).unwrap_or_else(|| {
+                            console::log(format!("Bad damage dice: {}", text.dice));
+                            UNARMED_DICE
+                        }),
+                    };
 
-                    log.entries
-                        .push(GameLog::battle_log(&name.name, &target_name.name, damage));
-                    SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                    // Enchanted weapons hit more often and harder; enchanted
+                    // shields turn blows aside.
+                    let weapon_bonus =
+                        slot_enchantment(&equipped, &enchantments, entity, EquipmentSlot::Melee);
+                    let shield_bonus = slot_enchantment(
+                        &equipped,
+                        &enchantments,
+                        wants_melee.target,
+                        EquipmentSlot::Shield,
+                    );
+                    let carried =
+                        carried_weight(&entities, &backpack, &weights, &quantities, entity);
+                    let roll = attack_roll(
+                        &mut rng,
+                        stats.power + weapon_bonus - burden_penalty(stats, carried),
+                        target_stats.defense + shield_bonus,
+                    );
+                    let mut damage = damage_roll(&mut rng, &dice, roll);
+                    if damage > 0 {
+                        damage = i32::max(0, damage + weapon_bonus);
+                    }
+                    let witnessed = entity == *player_entity
+                        || wants_melee.target == *player_entity
+                        || seen_by_player(&map, positions.get(entity))
+                        || seen_by_player(&map, positions.get(wants_melee.target));
+                    if witnessed {
+                        match roll {
+                            AttackRoll::Fumble => log.push(LogEvent::Fumble { attacker }),
+                            AttackRoll::Miss => log.push(LogEvent::Miss { attacker, target }),
+                            AttackRoll::Hit => log.push(LogEvent::Attack {
+                                attacker,
+                                target,
+                                damage,
+                            }),
+                            AttackRoll::Critical => log.push(LogEvent::Critical {
+                                attacker,
+                                target,
+                                damage,
+                            }),
+                        }
+                    }
+                    if damage > 0 {
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            wants_melee.target,
+                            damage,
+                            DamageType::Physical,
+                        );
+                    }
+                    if let Some(pos) = positions.get(entity) {
+                        noises.emit(Point::new(pos.x, pos.y), MELEE_NOISE_RADIUS);
+                    }
                 }
             }
         }
@@ -1,9 +1,133 @@
-use super::{Map, Monster, Paralyze, Position, RunState, SeenPlayer, Viewshed, WantsToMelee};
-use bracket_lib::prelude::{a_star_search, DistanceAlg, Point};
+use super::pathing::{self, PathMaps};
+use super::{
+    Abilities, AiState, CombatStats, Faction, FactionTable, GameLog, Group, LogEvent, Map, Monster,
+    MonsterAiState, Morale, Name, Noun, Paralyze, Position, Reaction, RunState, SeenPlayer,
+    Stealth, Viewshed, WantsToMelee, WantsToPickupItem, WantsToUseItem, PLAYER_FACTION,
+};
+use bracket_lib::prelude::{BaseMap, DistanceAlg, Point, RandomNumberGenerator};
 use specs::prelude::*;
+use std::collections::HashMap;
+
+const SEARCH_TURNS: i32 = 8;
+// Chance (1 in n) per turn that an idle monster gets up and starts wandering.
+const IDLE_WANDER_CHANCE: i32 = 10;
+
+pub struct Noise {
+    pub point: Point,
+    pub radius: i32,
+}
+
+// Sounds made since the last monster turn. Monsters within the radius
+// wake up and go to look at the source.
+pub struct Noises {
+    pub sounds: Vec<Noise>,
+}
+
+impl Default for Noises {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Noises {
+    pub fn new() -> Noises {
+        Noises { sounds: Vec::new() }
+    }
+
+    pub fn emit(&mut self, point: Point, radius: i32) {
+        self.sounds.push(Noise { point, radius });
+    }
+
+    fn heard_at(&self, point: Point) -> Option<Point> {
+        self.sounds
+            .iter()
+            .filter(|noise| {
+                DistanceAlg::Pythagoras.distance2d(point, noise.point) <= noise.radius as f32
+            })
+            .min_by_key(|noise| DistanceAlg::Pythagoras.distance2d(point, noise.point) as i32)
+            .map(|noise| noise.point)
+    }
+}
 
 pub struct MonsterAI {}
 
+// Take one step down the shared path map to `goal`; returns false when there
+// is no way forward. The goal may be occupied by another creature, so head
+// for it but never into it.
+fn step_towards(
+    map: &mut Map,
+    path_maps: &mut PathMaps,
+    pos: &mut Position,
+    viewshed: &mut Viewshed,
+    goal: Point,
+) -> bool {
+    let from = map.xy_idx(pos.x, pos.y);
+    match path_maps.step_toward(map, goal, from) {
+        Some(dest) => step_to(map, pos, viewshed, dest),
+        None => false,
+    }
+}
+
+fn step_to(map: &mut Map, pos: &mut Position, viewshed: &mut Viewshed, dest: usize) -> bool {
+    if !map.move_blocker(map.xy_idx(pos.x, pos.y), dest) {
+        return false;
+    }
+    pos.x = dest as i32 % map.width;
+    pos.y = dest as i32 / map.width;
+    viewshed.dirty = true;
+    true
+}
+
+fn random_step(
+    map: &mut Map,
+    pos: &mut Position,
+    viewshed: &mut Viewshed,
+    rng: &mut RandomNumberGenerator,
+) {
+    let exits = map.get_available_exits(map.xy_idx(pos.x, pos.y));
+    if exits.is_empty() {
+        return;
+    }
+    let (dest, _) = exits[(rng.roll_dice(1, exits.len() as i32) - 1) as usize];
+    step_to(map, pos, viewshed, dest);
+}
+
+fn random_room_center(map: &Map, rng: &mut RandomNumberGenerator) -> Option<Point> {
+    if map.rooms.is_empty() {
+        return None;
+    }
+    let room = &map.rooms[(rng.roll_dice(1, map.rooms.len() as i32) - 1) as usize];
+    let (x, y) = room.center();
+    Some(Point::new(x, y))
+}
+
+// Pack members spread over the free cells around their target instead of
+// queueing up behind each other; each one claims the closest unclaimed cell.
+fn claim_surround_slot(
+    map: &Map,
+    claims: &mut HashMap<i32, Vec<usize>>,
+    group: i32,
+    target: Point,
+    from: Point,
+) -> Option<Point> {
+    let claimed = claims.entry(group).or_default();
+    let slot = map
+        .get_available_exits(map.xy_idx(target.x, target.y))
+        .iter()
+        .map(|(idx, _)| *idx)
+        .filter(|idx| !claimed.contains(idx))
+        .map(|idx| Point::new(idx as i32 % map.width, idx as i32 / map.width))
+        .min_by_key(|p| (DistanceAlg::Pythagoras.distance2d(from, *p) * 100.0) as i32)?;
+    claimed.push(map.xy_idx(slot.x, slot.y));
+    Some(slot)
+}
+
+fn attack(wants_to_melee: &mut WriteStorage<WantsToMelee>, attacker: Entity, target: Entity) {
+    wants_to_melee
+        .insert(attacker, WantsToMelee { target })
+        .expect("Unable to insert attack");
+}
+
 impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (
@@ -11,13 +135,27 @@ impl<'a> System<'a> for MonsterAI {
         ReadExpect<'a, Point>,
         ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
+        ReadExpect<'a, FactionTable>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, Noises>,
+        WriteExpect<'a, GameLog>,
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, SeenPlayer>,
+        WriteStorage<'a, MonsterAiState>,
         ReadStorage<'a, Monster>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Stealth>,
+        ReadStorage<'a, Group>,
+        ReadStorage<'a, Morale>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Abilities>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, Paralyze>,
         WriteStorage<'a, WantsToMelee>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, WantsToPickupItem>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -26,23 +164,42 @@ impl<'a> System<'a> for MonsterAI {
             player_pos,
             player_entity,
             runstate,
+            faction_table,
+            mut rng,
+            mut noises,
+            mut log,
             entities,
             mut viewshed,
             mut seenplayers,
+            mut ai_states,
             monster,
+            factions,
+            stealths,
+            groups,
+            morales,
+            combat_stats,
+            names,
+            mut abilities,
             mut position,
             mut paralyze,
             mut wants_to_melee,
+            mut wants_to_use,
+            wants_pickup,
         ) = data;
 
         if *runstate != RunState::MonsterTurn {
             return;
         }
-        for (entity, mut viewshed, mut seenplayer, _monster, mut pos) in (
+        let mut path_maps = PathMaps::new(&mut map, *player_pos);
+        let mut surround_claims: HashMap<i32, Vec<usize>> = HashMap::new();
+
+        for (entity, viewshed, seenplayer, ai, _monster, faction, pos) in (
             &entities,
             &mut viewshed,
             &mut seenplayers,
+            &mut ai_states,
             &monster,
+            &factions,
             &mut position,
         )
             .join()
@@ -59,49 +216,267 @@ impl<'a> System<'a> for MonsterAI {
                     can_act = false;
                 }
             }
+            if let Some(abilities) = abilities.get_mut(entity) {
+                abilities.tick();
+            }
+            // MonsterItemSystem already spent this turn on an item.
+            if wants_to_use.get(entity).is_some() || wants_pickup.get(entity).is_some() {
+                can_act = false;
+            }
+
+            // Sort everyone in view by how our faction feels about them.
+            // Sleeping monsters see nothing; only noise wakes them.
+            let here = Point::new(pos.x, pos.y);
+            let mut target: Option<(Entity, Point)> = None;
+            let mut threats: Vec<Point> = Vec::new();
+            // Monsters already on the move keep track of what they see; the
+            // rest may overlook a stealthy creature.
+            let alert = matches!(
+                ai.state,
+                AiState::Hunting | AiState::Searching | AiState::Fleeing
+            );
+            if ai.state != AiState::Sleeping {
+                for tile in viewshed.visible_tiles.iter() {
+                    let idx = map.xy_idx(tile.x, tile.y);
+                    for other in map.tile_content[idx].iter() {
+                        if *other == entity {
+                            continue;
+                        }
+                        let other_faction = match factions.get(*other) {
+                            Some(other_faction) => other_faction,
+                            None => continue,
+                        };
+                        if combat_stats.get(*other).is_none_or(|s| s.hp < 1) {
+                            continue;
+                        }
+                        if let Some(stealth) = stealths.get(*other) {
+                            if !alert && rng.roll_dice(1, 100) <= stealth.value {
+                                continue;
+                            }
+                        }
+                        match faction_table.reaction(&faction.name, &other_faction.name) {
+                            Reaction::Attack => {
+                                let closer = match target {
+                                    None => true,
+                                    Some((_, p)) => {
+                                        DistanceAlg::Pythagoras.distance2d(here, *tile)
+                                            < DistanceAlg::Pythagoras.distance2d(here, p)
+                                    }
+                                };
+                                if closer {
+                                    target = Some((*other, *tile));
+                                }
+                            }
+                            Reaction::Flee => threats.push(*tile),
+                            Reaction::Ignore => {}
+                        }
+                    }
+                }
+            }
 
-            if viewshed.visible_tiles.contains(&*player_pos) {
-                seenplayer.point = Some(*player_pos);
+            if let Some((_, target_pos)) = target {
+                seenplayer.point = Some(target_pos);
+            }
+            let in_sight = map.visible_tiles[map.xy_idx(pos.x, pos.y)];
+            match ai.state {
+                AiState::Hunting | AiState::Fleeing => {}
+                _ if !threats.is_empty() => ai.state = AiState::Fleeing,
+                _ if target.is_some() => ai.state = AiState::Hunting,
+                _ => {
+                    if let Some(source) = noises.heard_at(here) {
+                        if ai.state == AiState::Sleeping && in_sight {
+                            log.push(LogEvent::WakeUp {
+                                actor: Noun::of(&names, entity, *player_entity),
+                            });
+                        }
+                        ai.state = AiState::Searching;
+                        ai.destination = Some(source);
+                        ai.search_turns = SEARCH_TURNS;
+                    }
+                }
             }
 
-            if can_act {
-                match seenplayer.point {
-                    None => {}
-                    Some(seen_player_pos) => {
-                        let distance = DistanceAlg::Pythagoras
-                            .distance2d(Point::new(pos.x, pos.y), *player_pos);
-                        if distance < 1.5 {
-                            wants_to_melee
-                                .insert(
-                                    entity,
-                                    WantsToMelee {
-                                        target: *player_entity,
-                                    },
-                                )
-                                .expect("Unable to insert attack");
-                        } else {
-                            let path = a_star_search(
-                                map.xy_idx(pos.x, pos.y),
-                                map.xy_idx(seen_player_pos.x, seen_player_pos.y),
-                                &mut *map,
-                            );
-                            if path.success && path.steps.len() > 1 {
-                                let mut idx = map.xy_idx(pos.x, pos.y);
-                                map.blocked[idx] = false;
-                                pos.x = path.steps[1] as i32 % map.width;
-                                pos.y = path.steps[1] as i32 / map.width;
-                                idx = map.xy_idx(pos.x, pos.y);
-                                map.blocked[idx] = true;
-                                viewshed.dirty = true;
+            let wounded = match (combat_stats.get(entity), morales.get(entity)) {
+                (Some(stats), Some(morale)) => stats.hp * 100 < stats.max_hp * morale.flee_percent,
+                _ => false,
+            };
+            if ai.state == AiState::Hunting && (wounded || !threats.is_empty()) {
+                ai.state = AiState::Fleeing;
+                if in_sight {
+                    log.push(LogEvent::Flee {
+                        actor: Noun::of(&names, entity, *player_entity),
+                    });
+                }
+            } else if ai.state == AiState::Fleeing && !wounded && threats.is_empty() {
+                if target.is_some() {
+                    ai.state = AiState::Hunting;
+                } else {
+                    ai.state = AiState::Searching;
+                    ai.destination = None;
+                    ai.search_turns = SEARCH_TURNS;
+                }
+                if in_sight {
+                    log.push(LogEvent::Rally {
+                        actor: Noun::of(&names, entity, *player_entity),
+                    });
+                }
+            }
+
+            if !can_act {
+                continue;
+            }
+
+            match ai.state {
+                AiState::Sleeping => {}
+                AiState::Idle => {
+                    if rng.roll_dice(1, IDLE_WANDER_CHANCE) == 1 {
+                        ai.state = AiState::Wandering;
+                        ai.destination = None;
+                    }
+                }
+                AiState::Wandering if faction.name == PLAYER_FACTION => {
+                    // Allies tag along with the player instead of patrolling.
+                    if DistanceAlg::Pythagoras.distance2d(here, *player_pos) > 2.0 {
+                        step_towards(&mut map, &mut path_maps, pos, viewshed, *player_pos);
+                    }
+                }
+                AiState::Wandering => {
+                    // Patrol between room centers, falling back to a random walk.
+                    let arrived = match ai.destination {
+                        None => true,
+                        Some(dest) => dest.x == pos.x && dest.y == pos.y,
+                    };
+                    if arrived {
+                        ai.destination = random_room_center(&map, &mut rng);
+                    }
+                    let moved = match ai.destination {
+                        None => false,
+                        Some(dest) => step_towards(&mut map, &mut path_maps, pos, viewshed, dest),
+                    };
+                    if !moved {
+                        ai.destination = None;
+                        random_step(&mut map, pos, viewshed, &mut rng);
+                    }
+                }
+                AiState::Hunting => match seenplayer.point {
+                    None => {
+                        ai.state = AiState::Searching;
+                        ai.search_turns = SEARCH_TURNS;
+                    }
+                    Some(seen_pos) => {
+                        let distance = DistanceAlg::Pythagoras.distance2d(here, seen_pos);
+                        // Abilities are for range; in melee the monster just hits.
+                        // The viewshed is a field of view, so seeing the target
+                        // already means a clear line of sight.
+                        let ability = match abilities.get_mut(entity) {
+                            Some(abilities) if target.is_some() && distance >= 1.5 => {
+                                abilities.use_ready(distance)
+                            }
+                            _ => None,
+                        };
+                        match (target, ability) {
+                            (Some(_), Some(effect)) => {
+                                wants_to_use
+                                    .insert(
+                                        entity,
+                                        WantsToUseItem {
+                                            item: effect,
+                                            target: Some(seen_pos),
+                                        },
+                                    )
+                                    .expect("Unable to insert intent");
+                                log.push(LogEvent::Cast {
+                                    caster: Noun::of(&names, entity, *player_entity),
+                                    ability: Noun::of(&names, effect, *player_entity),
+                                });
+                            }
+                            (Some((target_entity, _)), None) if distance < 1.5 => {
+                                attack(&mut wants_to_melee, entity, target_entity);
+                            }
+                            _ => {
+                                let slot = match (target, groups.get(entity)) {
+                                    (Some(_), Some(group)) => claim_surround_slot(
+                                        &map,
+                                        &mut surround_claims,
+                                        group.id,
+                                        seen_pos,
+                                        here,
+                                    ),
+                                    _ => None,
+                                };
+                                let surrounding = match slot {
+                                    Some(slot) => {
+                                        step_towards(&mut map, &mut path_maps, pos, viewshed, slot)
+                                    }
+                                    None => false,
+                                };
+                                if !surrounding {
+                                    step_towards(&mut map, &mut path_maps, pos, viewshed, seen_pos);
+                                }
                             }
                         }
 
-                        if seen_player_pos.x == pos.x && seen_player_pos.y == pos.y {
+                        // Reached the last known position without seeing the target.
+                        if seen_pos.x == pos.x && seen_pos.y == pos.y {
                             seenplayer.point = None;
+                            ai.state = AiState::Searching;
+                            ai.destination = None;
+                            ai.search_turns = SEARCH_TURNS;
+                        }
+                    }
+                },
+                AiState::Fleeing => {
+                    // Run from whatever scares us, or from the last enemy seen.
+                    let mut sources = threats.clone();
+                    if sources.is_empty() {
+                        if let Some(seen_pos) = seenplayer.point {
+                            sources.push(seen_pos);
+                        }
+                    }
+                    let from = map.xy_idx(pos.x, pos.y);
+                    let dest = if sources == [path_maps.player] {
+                        path_maps.step_away_from_player(&map, from)
+                    } else if sources.is_empty() {
+                        None
+                    } else {
+                        let starts: Vec<usize> =
+                            sources.iter().map(|p| map.xy_idx(p.x, p.y)).collect();
+                        let flee_map = pathing::walls_only_dijkstra(&mut map, &starts);
+                        pathing::step_up(&flee_map, &map, from)
+                    };
+                    let fled = match dest {
+                        Some(dest) => step_to(&mut map, pos, viewshed, dest),
+                        None => false,
+                    };
+                    if !fled {
+                        // Cornered: turn and fight.
+                        if let Some((target_entity, target_pos)) = target {
+                            if DistanceAlg::Pythagoras.distance2d(here, target_pos) < 1.5 {
+                                attack(&mut wants_to_melee, entity, target_entity);
+                            }
                         }
                     }
                 }
+                AiState::Searching => {
+                    let moved = match ai.destination {
+                        Some(dest) if dest.x != pos.x || dest.y != pos.y => {
+                            step_towards(&mut map, &mut path_maps, pos, viewshed, dest)
+                        }
+                        _ => false,
+                    };
+                    if !moved {
+                        ai.destination = None;
+                        random_step(&mut map, pos, viewshed, &mut rng);
+                    }
+                    ai.search_turns -= 1;
+                    if ai.search_turns < 1 {
+                        ai.state = AiState::Wandering;
+                        ai.destination = None;
+                    }
+                }
             }
         }
+
+        noises.sounds.clear();
     }
 }
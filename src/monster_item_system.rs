@@ -0,0 +1,167 @@
+use super::{
+    carried_weight, carry_limit, item_weight, AiState, AreaOfEffect, Charges, CombatStats,
+    Consumable, Faction, FactionTable, InBackpack, InflictsDamage, Item, Map, MonsterAiState,
+    Paralyze, Position, ProvidesHealing, Quantity, Ranged, Reaction, RunState, Viewshed,
+    WantsToPickupItem, WantsToUseItem, Weight, PLAYER_FACTION,
+};
+use bracket_lib::prelude::{DistanceAlg, Point};
+use specs::prelude::*;
+
+// Monsters drink a healing potion once they drop below this share of their HP.
+const DRINK_PERCENT: i32 = 50;
+
+// Runs before MonsterAI. A monster that picks up or uses something here
+// spends its turn on it and MonsterAI leaves it alone.
+pub struct MonsterItemSystem {}
+
+impl<'a> System<'a> for MonsterItemSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, RunState>,
+        ReadExpect<'a, FactionTable>,
+        Entities<'a>,
+        ReadStorage<'a, MonsterAiState>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Paralyze>,
+        ReadStorage<'a, Item>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Consumable>,
+        ReadStorage<'a, ProvidesHealing>,
+        ReadStorage<'a, InflictsDamage>,
+        ReadStorage<'a, Ranged>,
+        ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, Charges>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Quantity>,
+        WriteStorage<'a, WantsToPickupItem>,
+        WriteStorage<'a, WantsToUseItem>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            player_pos,
+            runstate,
+            faction_table,
+            entities,
+            ai_states,
+            viewsheds,
+            positions,
+            factions,
+            combat_stats,
+            paralyze,
+            items,
+            backpack,
+            consumables,
+            heal_items,
+            inflict_damage,
+            ranged,
+            aoe,
+            charges,
+            weights,
+            quantities,
+            mut wants_pickup,
+            mut wants_use,
+        ) = data;
+
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (entity, ai, viewshed, pos, faction, stats) in (
+            &entities,
+            &ai_states,
+            &viewsheds,
+            &positions,
+            &factions,
+            &combat_stats,
+        )
+            .join()
+        {
+            if ai.state == AiState::Sleeping || paralyze.get(entity).is_some() {
+                continue;
+            }
+            let carried: Vec<Entity> = (&entities, &backpack)
+                .join()
+                .filter(|(_, pack)| pack.owner == entity)
+                .map(|(item, _)| item)
+                .collect();
+
+            // Drink first: a wounded monster cares more about staying alive.
+            if stats.hp * 100 < stats.max_hp * DRINK_PERCENT {
+                let potion = carried.iter().find(|item| heal_items.get(**item).is_some());
+                if let Some(potion) = potion {
+                    wants_use
+                        .insert(
+                            entity,
+                            WantsToUseItem {
+                                item: *potion,
+                                target: None,
+                            },
+                        )
+                        .expect("Unable to insert intent");
+                    continue;
+                }
+            }
+
+            // Read an offensive scroll or zap a wand at the player if it
+            // reaches without catching the user in the blast.
+            let hostile = faction_table.reaction(&faction.name, PLAYER_FACTION) == Reaction::Attack;
+            let sees_player = viewshed.visible_tiles.contains(&*player_pos);
+            if hostile && sees_player {
+                let distance =
+                    DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+                let scroll = carried.iter().find(|item| {
+                    let offensive =
+                        inflict_damage.get(**item).is_some() || paralyze.get(**item).is_some();
+                    let in_range = ranged
+                        .get(**item)
+                        .is_some_and(|r| distance <= r.range as f32);
+                    let safe = aoe.get(**item).is_none_or(|a| (a.radius as f32) < distance);
+                    let usable = consumables.get(**item).is_some()
+                        || charges.get(**item).is_some_and(|c| c.current > 0);
+                    usable && offensive && in_range && safe
+                });
+                if let Some(scroll) = scroll {
+                    wants_use
+                        .insert(
+                            entity,
+                            WantsToUseItem {
+                                item: *scroll,
+                                target: Some(*player_pos),
+                            },
+                        )
+                        .expect("Unable to insert intent");
+                    continue;
+                }
+            }
+
+            // Pick up whatever lies underfoot, unless running for its life.
+            if ai.state == AiState::Fleeing {
+                continue;
+            }
+            let idx = map.xy_idx(pos.x, pos.y);
+            let carried = carried_weight(&entities, &backpack, &weights, &quantities, entity);
+            let loot = map.tile_content[idx].iter().find(|other| {
+                items.get(**other).is_some()
+                    && carried + item_weight(&weights, &quantities, **other) <= carry_limit(stats)
+            });
+            if let Some(item) = loot {
+                wants_pickup
+                    .insert(
+                        entity,
+                        WantsToPickupItem {
+                            collected_by: entity,
+                            item: *item,
+                        },
+                    )
+                    .expect("Unable to insert want to pickup");
+            }
+        }
+    }
+}
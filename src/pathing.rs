@@ -0,0 +1,67 @@
+use super::Map;
+use bracket_lib::prelude::{BaseMap, DijkstraMap, Point};
+use std::collections::HashMap;
+
+const PATH_MAP_DEPTH: f32 = 64.0;
+
+// Dijkstra map over walls only. Creatures standing in a corridor don't
+// cut the distances behind them; they are dodged when actually stepping.
+pub fn walls_only_dijkstra(map: &mut Map, starts: &[usize]) -> DijkstraMap {
+    let occupied = map.blocked.clone();
+    map.populate_blocked();
+    let dijkstra = DijkstraMap::new(map.width, map.height, starts, &*map, PATH_MAP_DEPTH);
+    map.blocked = occupied;
+    dijkstra
+}
+
+// Free neighbour that gets closest to the map's starts.
+pub fn step_down(dijkstra: &DijkstraMap, map: &Map, from: usize) -> Option<usize> {
+    map.get_available_exits(from)
+        .iter()
+        .map(|(idx, _)| *idx)
+        .filter(|idx| dijkstra.map[*idx] < dijkstra.map[from])
+        .min_by(|a, b| dijkstra.map[*a].partial_cmp(&dijkstra.map[*b]).unwrap())
+}
+
+// Free neighbour that gets furthest from the map's starts; None when cornered.
+pub fn step_up(dijkstra: &DijkstraMap, map: &Map, from: usize) -> Option<usize> {
+    map.get_available_exits(from)
+        .iter()
+        .map(|(idx, _)| *idx)
+        .filter(|idx| dijkstra.map[*idx] > dijkstra.map[from])
+        .max_by(|a, b| dijkstra.map[*a].partial_cmp(&dijkstra.map[*b]).unwrap())
+}
+
+// Built once per monster turn and shared by every monster, instead of each
+// of them running A*. The map toward the player is made up front; the ones
+// toward room centres, noises and last known positions are made the first
+// time a monster heads there, so a pack chasing one point pays for it once.
+pub struct PathMaps {
+    pub player: Point,
+    toward: HashMap<usize, DijkstraMap>,
+}
+
+impl PathMaps {
+    pub fn new(map: &mut Map, player: Point) -> PathMaps {
+        let start = map.xy_idx(player.x, player.y);
+        let mut toward = HashMap::new();
+        toward.insert(start, walls_only_dijkstra(map, &[start]));
+        PathMaps { player, toward }
+    }
+
+    // None when there is no way forward: the goal is out of reach or every
+    // step closer is taken.
+    pub fn step_toward(&mut self, map: &mut Map, goal: Point, from: usize) -> Option<usize> {
+        let goal_idx = map.xy_idx(goal.x, goal.y);
+        let dijkstra = self
+            .toward
+            .entry(goal_idx)
+            .or_insert_with(|| walls_only_dijkstra(map, &[goal_idx]));
+        step_down(dijkstra, map, from)
+    }
+
+    pub fn step_away_from_player(&self, map: &Map, from: usize) -> Option<usize> {
+        let start = map.xy_idx(self.player.x, self.player.y);
+        step_up(&self.toward[&start], map, from)
+    }
+}
@@ -4,9 +4,12 @@ use specs_derive::*;
 use std::cmp::{max, min};
 
 use super::{
-    CombatStats, DropItem, GameLog, InBackpack, Item, Map, Name, Point, Position, Ranged, RunState,
-    TileType, Viewshed, WantsToMelee, WantsToPickupItem, WantsToUseItem, MAPSIZE_HEIGHT,
-    MAPSIZE_WIDTH,
+    carried_weight, carry_capacity,
+    ranged_combat_system::{equipped_launcher, THROW_RANGE},
+    spawner, Ammunition, CombatStats, Cursed, DropItem, Enchantment, Equipped, Faction, GameLog,
+    Gold, Identification, InBackpack, InventorySlot, Item, Launcher, LogEvent, Map, Merchant, Name,
+    Noun, Point, Position, Purse, Quantity, Ranged, RunState, TargetAction, TileType, Viewshed,
+    WantsToMelee, WantsToPickupItem, WantsToUseItem, Weight, MAPSIZE_HEIGHT, MAPSIZE_WIDTH,
 };
 
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
@@ -17,10 +20,17 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let combat_states = ecs.read_storage::<CombatStats>();
+    let factions = ecs.read_storage::<Faction>();
+    let merchants = ecs.read_storage::<Merchant>();
     let mut want_to_melee = ecs.write_storage::<WantsToMelee>();
     let entities = ecs.entities();
     let map = ecs.fetch::<Map>();
 
+    // An ally in the way trades places instead of being attacked.
+    let mut swap_with: Option<(Entity, Position)> = None;
+    // Where the player ended up, whether by stepping or by swapping places.
+    let mut moved_to: Option<usize> = None;
+
     for (entity, _player, pos, viewshed) in
         (&entities, &mut players, &mut positions, &mut viewsheds).join()
     {
@@ -34,10 +44,23 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            if merchants.get(*potential_target).is_some() {
+                return RunState::ShowTrade {
+                    merchant: *potential_target,
+                };
+            }
+            let is_ally = match (factions.get(entity), factions.get(*potential_target)) {
+                (Some(mine), Some(theirs)) => mine.name == theirs.name,
+                _ => false,
+            };
+            if is_ally {
+                swap_with = Some((*potential_target, *pos));
+                break;
+            }
             let target = combat_states.get(*potential_target);
             match target {
                 None => {}
-                Some(t) => {
+                Some(_) => {
                     want_to_melee
                         .insert(
                             entity,
@@ -51,7 +74,7 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
             }
         }
 
-        if !map.blocked[destination_idx] {
+        if swap_with.is_some() || !map.blocked[destination_idx] {
             pos.x = min(MAPSIZE_WIDTH as i32, max(0, pos.x + delta_x));
             pos.y = min(MAPSIZE_HEIGHT as i32, max(0, pos.y + delta_y));
 
@@ -60,8 +83,36 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
             ppos.y = pos.y;
 
             viewshed.dirty = true;
+            moved_to = Some(destination_idx);
+            break;
+        }
+    }
 
-            return RunState::PlayerTurn;
+    if let Some((ally, old_player_pos)) = swap_with {
+        if let Some(ally_pos) = positions.get_mut(ally) {
+            *ally_pos = old_player_pos;
+        }
+        if let Some(ally_viewshed) = viewsheds.get_mut(ally) {
+            ally_viewshed.dirty = true;
+        }
+    }
+
+    // Gold needs no pickup command; walking over a pile takes it.
+    if let Some(idx) = moved_to {
+        let player_entity = ecs.fetch::<Entity>();
+        let piles = ecs.read_storage::<Gold>();
+        let mut purses = ecs.write_storage::<Purse>();
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        for pile in map.tile_content[idx].iter() {
+            if let Some(gold) = piles.get(*pile) {
+                if let Some(purse) = purses.get_mut(*player_entity) {
+                    purse.gold += gold.amount;
+                }
+                gamelog.push(LogEvent::PickUpGold {
+                    amount: gold.amount,
+                });
+                entities.delete(*pile).expect("Delete failed");
+            }
         }
     }
     RunState::PlayerTurn
@@ -75,7 +126,7 @@ pub fn try_next_level(ecs: &mut World) -> RunState {
         RunState::NextLevel
     } else {
         let mut gamelog = ecs.fetch_mut::<GameLog>();
-        gamelog.entries.push(GameLog::cannot_down_log());
+        gamelog.push(LogEvent::CannotDescend);
         RunState::AwaitingInput
     }
 }
@@ -96,7 +147,7 @@ pub fn get_item(ecs: &mut World) -> RunState {
     }
 
     match target_item {
-        None => gamelog.entries.push(GameLog::try_get_but_nothing_log()),
+        None => gamelog.push(LogEvent::NothingToPickUp),
         Some(item) => {
             let mut pickup = ecs.write_storage::<WantsToPickupItem>();
             pickup
@@ -113,20 +164,56 @@ pub fn get_item(ecs: &mut World) -> RunState {
     RunState::PlayerTurn
 }
 
-pub fn try_use_item(ecs: &mut World, keynum: i32, inventory_window_show: &mut bool) -> RunState {
+pub fn inventory_page_count(ecs: &World) -> i32 {
+    let player_entity = *ecs.fetch::<Entity>();
+    backpack_page_count(ecs, player_entity)
+}
+
+pub fn backpack_page_count(ecs: &World, owner: Entity) -> i32 {
+    let backpack = ecs.read_storage::<InBackpack>();
+    let slots = ecs.read_storage::<InventorySlot>();
+
+    let last_page = (&backpack, &slots)
+        .join()
+        .filter(|item| item.0.owner == owner)
+        .map(|item| item.1.page())
+        .max()
+        .unwrap_or(0);
+    last_page + 1
+}
+
+// What the player carries and how much they can carry before being burdened.
+pub fn player_load(ecs: &World) -> (i32, i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let weights = ecs.read_storage::<Weight>();
+    let quantities = ecs.read_storage::<Quantity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+
+    let carried = carried_weight(
+        &ecs.entities(),
+        &backpack,
+        &weights,
+        &quantities,
+        *player_entity,
+    );
+    let capacity = combat_stats.get(*player_entity).map_or(0, carry_capacity);
+    (carried, capacity)
+}
+
+pub fn try_use_item(ecs: &mut World, slot: i32, inventory_window_show: &mut bool) -> RunState {
     *inventory_window_show = false;
     let player_entity = ecs.fetch::<Entity>();
-    let names = ecs.read_storage::<Name>();
+    let slots = ecs.read_storage::<InventorySlot>();
     let backpack = ecs.read_storage::<InBackpack>();
     let mut gamelog = ecs.fetch_mut::<GameLog>();
     let entities = ecs.entities();
 
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
+    for (entity, _pack, inventory_slot) in (&entities, &backpack, &slots)
         .join()
         .filter(|item| item.1.owner == *player_entity)
     {
-        if j == keynum {
+        if inventory_slot.slot == slot {
             let is_ranged = ecs.read_storage::<Ranged>();
             let item_raged = is_ranged.get(entity);
             match item_raged {
@@ -147,38 +234,144 @@ pub fn try_use_item(ecs: &mut World, keynum: i32, inventory_window_show: &mut bo
                     return RunState::ShowTargeting {
                         range: item_raged.range,
                         item: entity,
+                        action: TargetAction::Use,
                     }
                 }
             }
         }
-        j += 1;
     }
-    gamelog.entries.push(GameLog::try_do_item_but_no_item());
+    gamelog.push(LogEvent::NoSuchItem);
     RunState::AwaitingInput
 }
 
-pub fn try_drop_item(ecs: &mut World, keynum: i32, inventory_window_show: &mut bool) -> RunState {
+pub fn try_throw_item(ecs: &mut World, slot: i32, inventory_window_show: &mut bool) -> RunState {
     *inventory_window_show = false;
     let player_entity = ecs.fetch::<Entity>();
-    let names = ecs.read_storage::<Name>();
+    let slots = ecs.read_storage::<InventorySlot>();
     let backpack = ecs.read_storage::<InBackpack>();
     let mut gamelog = ecs.fetch_mut::<GameLog>();
     let entities = ecs.entities();
 
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
+    for (entity, _pack, inventory_slot) in (&entities, &backpack, &slots)
         .join()
         .filter(|item| item.1.owner == *player_entity)
     {
-        if j == keynum {
+        if inventory_slot.slot == slot {
+            if refuse_cursed(ecs, &mut gamelog, entity) {
+                return RunState::AwaitingInput;
+            }
+            return RunState::ShowTargeting {
+                range: THROW_RANGE,
+                item: entity,
+                action: TargetAction::Throw,
+            };
+        }
+    }
+    gamelog.push(LogEvent::NoSuchItem);
+    RunState::AwaitingInput
+}
+
+// Aims the equipped bow or sling, loaded with the first matching ammunition.
+pub fn try_fire(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let launcher = match equipped_launcher(ecs, player_entity) {
+        Some(launcher) => launcher,
+        None => {
+            ecs.fetch_mut::<GameLog>().push(LogEvent::NoLauncher);
+            return RunState::AwaitingInput;
+        }
+    };
+    let launchers = ecs.read_storage::<Launcher>();
+    let launcher = launchers.get(launcher).unwrap();
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+
+    let ammo = (&entities, &backpack, &ammunition)
+        .join()
+        .find(|(_, pack, ammo)| pack.owner == player_entity && ammo.kind == launcher.ammo)
+        .map(|(entity, _, _)| entity);
+    match ammo {
+        Some(ammo) => RunState::ShowTargeting {
+            range: launcher.range,
+            item: ammo,
+            action: TargetAction::Fire,
+        },
+        None => {
+            ecs.fetch_mut::<GameLog>().push(LogEvent::NoAmmo);
+            RunState::AwaitingInput
+        }
+    }
+}
+
+pub fn try_drop_item(ecs: &mut World, slot: i32, inventory_window_show: &mut bool) -> RunState {
+    *inventory_window_show = false;
+    let player_entity = ecs.fetch::<Entity>();
+    let slots = ecs.read_storage::<InventorySlot>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    let entities = ecs.entities();
+
+    for (entity, _pack, inventory_slot) in (&entities, &backpack, &slots)
+        .join()
+        .filter(|item| item.1.owner == *player_entity)
+    {
+        if inventory_slot.slot == slot {
+            if refuse_cursed(ecs, &mut gamelog, entity) {
+                return RunState::AwaitingInput;
+            }
+            // Ask how many to drop from a stack; all of them unless told otherwise.
+            if let Some(stack) = ecs.read_storage::<Quantity>().get(entity) {
+                if stack.amount > 1 {
+                    return RunState::ShowDropCount {
+                        item: entity,
+                        count: stack.amount,
+                        max: stack.amount,
+                    };
+                }
+            }
             let mut intent = ecs.write_storage::<DropItem>();
             intent
                 .insert(*ecs.fetch::<Entity>(), DropItem { item: entity })
                 .expect("Cannot DropItem");
             return RunState::PlayerTurn;
         }
-        j += 1;
     }
-    gamelog.entries.push(GameLog::try_do_item_but_no_item());
+    gamelog.push(LogEvent::NoSuchItem);
     RunState::AwaitingInput
 }
+
+// Worn cursed items can't be dropped or thrown; says so and returns true.
+fn refuse_cursed(ecs: &World, gamelog: &mut GameLog, item: Entity) -> bool {
+    let stuck = ecs.read_storage::<Equipped>().get(item).is_some()
+        && ecs.read_storage::<Cursed>().get(item).is_some();
+    if stuck {
+        gamelog.push(LogEvent::Cursed {
+            item: Noun::item(
+                &ecs.read_storage::<Name>(),
+                &ecs.fetch::<Identification>(),
+                &ecs.read_storage::<Enchantment>(),
+                item,
+            ),
+        });
+    }
+    stuck
+}
+
+// Drops `count` off the top of a stack, leaving the rest in the backpack.
+pub fn drop_count(ecs: &mut World, item: Entity, count: i32) -> RunState {
+    let whole = ecs
+        .read_storage::<Quantity>()
+        .get(item)
+        .is_none_or(|stack| count >= stack.amount);
+    let dropped = if whole {
+        item
+    } else {
+        spawner::split_stack(ecs, item, count)
+    };
+    let player_entity = *ecs.fetch::<Entity>();
+    ecs.write_storage::<DropItem>()
+        .insert(player_entity, DropItem { item: dropped })
+        .expect("Cannot DropItem");
+    RunState::PlayerTurn
+}
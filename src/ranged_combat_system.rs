@@ -0,0 +1,277 @@
+use super::{
+    attack_roll, burden_penalty, carried_weight, damage_roll, gamelog::seen_by_player,
+    slot_enchantment, spawner, Ammunition, AttackRoll, CombatStats, DamageDice, DamageType, Dice,
+    Enchantment, EquipmentSlot, Equipped, GameLog, Identification, InBackpack, InventorySlot,
+    Launcher, LogEvent, Map, Name, Noun, Position, Quantity, SufferDamage, TileType, WantsToThrow,
+    Weight,
+};
+use bracket_lib::prelude::{console, line2d, LineAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+pub const THROW_RANGE: i32 = 6;
+// Whatever is thrown without dice of its own just bruises.
+const THROWN_DICE: Dice = Dice {
+    count: 1,
+    sides: 2,
+    bonus: 0,
+};
+// Chance (1 in n) that a piece of ammunition breaks where it lands.
+const AMMO_BREAK_CHANCE: i32 = 4;
+
+pub fn equipped_launcher(ecs: &World, owner: Entity) -> Option<Entity> {
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    let launchers = ecs.read_storage::<Launcher>();
+    (&entities, &equipped, &launchers)
+        .join()
+        .find(|(_, e, _)| e.owner == owner && e.slot == EquipmentSlot::Ranged)
+        .map(|(entity, _, _)| entity)
+}
+
+// Thrown items and fired ammunition fly along a Bresenham line and stop at
+// the first creature or wall in the way. Runs on the World because a shot
+// from a stack leaves a new item on the floor.
+pub fn resolve_missiles(ecs: &mut World) {
+    let throws: Vec<(Entity, WantsToThrow)> = {
+        let entities = ecs.entities();
+        let wants_throw = ecs.read_storage::<WantsToThrow>();
+        (&entities, &wants_throw)
+            .join()
+            .map(|(entity, throw)| (entity, throw.clone()))
+            .collect()
+    };
+    ecs.write_storage::<WantsToThrow>().clear();
+
+    for (thrower, throw) in throws.iter() {
+        fly(ecs, *thrower, throw);
+    }
+}
+
+fn fly(ecs: &mut World, thrower: Entity, throw: &WantsToThrow) {
+    let from = match ecs.read_storage::<Position>().get(thrower) {
+        Some(pos) => Point::new(pos.x, pos.y),
+        None => return,
+    };
+    let player_entity = *ecs.fetch::<Entity>();
+    let launcher = if throw.fired {
+        equipped_launcher(ecs, thrower)
+    } else {
+        None
+    };
+    let item_noun = Noun::item(
+        &ecs.read_storage::<Name>(),
+        &ecs.fetch::<Identification>(),
+        &ecs.read_storage::<Enchantment>(),
+        throw.item,
+    );
+
+    // One piece comes off a stack; anything else leaves the backpack whole.
+    let stacked = ecs
+        .read_storage::<Quantity>()
+        .get(throw.item)
+        .is_some_and(|stack| stack.amount > 1);
+    let missile = if stacked {
+        spawner::split_stack(ecs, throw.item, 1)
+    } else {
+        ecs.write_storage::<InBackpack>().remove(throw.item);
+        ecs.write_storage::<InventorySlot>().remove(throw.item);
+        ecs.write_storage::<Equipped>().remove(throw.item);
+        throw.item
+    };
+
+    let (landing, victim) = {
+        let map = ecs.fetch::<Map>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let mut landing = from;
+        let mut victim: Option<Entity> = None;
+        for point in line2d(LineAlg::Bresenham, from, throw.target)
+            .into_iter()
+            .filter(|point| *point != from)
+        {
+            if point.x < 0 || point.x >= map.width || point.y < 0 || point.y >= map.height {
+                break;
+            }
+            let idx = map.xy_idx(point.x, point.y);
+            if map.tiles[idx].tiletype == TileType::Wall {
+                break;
+            }
+            landing = point;
+            victim = map.tile_content[idx]
+                .iter()
+                .find(|other| combat_stats.get(**other).is_some_and(|s| s.hp > 0))
+                .copied();
+            if victim.is_some() {
+                break;
+            }
+        }
+        (landing, victim)
+    };
+
+    {
+        let map = ecs.fetch::<Map>();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let mut log = ecs.write_resource::<GameLog>();
+        if thrower == player_entity || seen_by_player(&map, positions.get(thrower)) {
+            log.push(LogEvent::Throw {
+                actor: Noun::of(&names, thrower, player_entity),
+                item: item_noun.clone(),
+                fired: launcher.is_some(),
+            });
+        }
+    }
+
+    if let Some(victim) = victim {
+        hit(
+            ecs,
+            thrower,
+            victim,
+            launcher.unwrap_or(missile),
+            launcher.is_some(),
+        );
+    }
+    land(ecs, missile, landing, item_noun);
+}
+
+fn hit(ecs: &mut World, thrower: Entity, victim: Entity, weapon: Entity, launcher: bool) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let damage_dice = ecs.read_storage::<DamageDice>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let enchantments = ecs.read_storage::<Enchantment>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let weights = ecs.read_storage::<Weight>();
+    let quantities = ecs.read_storage::<Quantity>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut log = ecs.write_resource::<GameLog>();
+    let map = ecs.fetch::<Map>();
+    let positions = ecs.read_storage::<Position>();
+
+    let carried = carried_weight(&ecs.entities(), &backpack, &weights, &quantities, thrower);
+    let (power, defense) = match (combat_stats.get(thrower), combat_stats.get(victim)) {
+        (Some(attacker), Some(target)) => (
+            attacker.power - burden_penalty(attacker, carried),
+            target.defense,
+        ),
+        _ => return,
+    };
+    let dice = match damage_dice.get(weapon) {
+        None => THROWN_DICE,
+        Some(text) => Dice::parse(&text.dice).unwrap_or_else(|| {
+            console::log(format!("Bad damage dice: {}", text.dice));
+            THROWN_DICE
+        }),
+    };
+
+    let attacker = Noun::of(&names, thrower, player_entity);
+    let target = Noun::of(&names, victim, player_entity);
+    // Only a launcher's enchantment helps a shot; thrown things are just thrown.
+    let weapon_bonus = if launcher {
+        slot_enchantment(&equipped, &enchantments, thrower, EquipmentSlot::Ranged)
+    } else {
+        0
+    };
+    let shield_bonus = slot_enchantment(&equipped, &enchantments, victim, EquipmentSlot::Shield);
+    let roll = attack_roll(&mut rng, power + weapon_bonus, defense + shield_bonus);
+    let mut damage = damage_roll(&mut rng, &dice, roll);
+    if damage > 0 {
+        damage = i32::max(0, damage + weapon_bonus);
+    }
+    let witnessed = thrower == player_entity
+        || victim == player_entity
+        || seen_by_player(&map, positions.get(thrower))
+        || seen_by_player(&map, positions.get(victim));
+    if witnessed {
+        match roll {
+            AttackRoll::Fumble => log.push(LogEvent::Fumble { attacker }),
+            AttackRoll::Miss => log.push(LogEvent::Miss { attacker, target }),
+            AttackRoll::Hit => log.push(LogEvent::Attack {
+                attacker,
+                target,
+                damage,
+            }),
+            AttackRoll::Critical => log.push(LogEvent::Critical {
+                attacker,
+                target,
+                damage,
+            }),
+        }
+    }
+    if damage > 0 {
+        SufferDamage::new_damage(
+            &mut ecs.write_storage::<SufferDamage>(),
+            victim,
+            damage,
+            DamageType::Physical,
+        );
+    }
+}
+
+fn land(ecs: &mut World, item: Entity, landing: Point, item_noun: Noun) {
+    let is_ammo = ecs.read_storage::<Ammunition>().get(item).is_some();
+    let broke = is_ammo
+        && ecs
+            .write_resource::<RandomNumberGenerator>()
+            .roll_dice(1, AMMO_BREAK_CHANCE)
+            == 1;
+    if broke {
+        {
+            let map = ecs.fetch::<Map>();
+            let pos = Position {
+                x: landing.x,
+                y: landing.y,
+            };
+            if seen_by_player(&map, Some(&pos)) {
+                ecs.write_resource::<GameLog>()
+                    .push(LogEvent::Break { item: item_noun });
+            }
+        }
+        ecs.delete_entity(item).expect("Unable to delete");
+        return;
+    }
+
+    // Land on a pile of the same thing if there is one.
+    let pile = {
+        let entities = ecs.entities();
+        let positions = ecs.read_storage::<Position>();
+        let names = ecs.read_storage::<Name>();
+        let quantities = ecs.read_storage::<Quantity>();
+        let item_name = names.get(item).map(|name| name.name.to_string());
+        match (quantities.get(item), item_name) {
+            (Some(_), Some(item_name)) => (&entities, &positions, &names, &quantities)
+                .join()
+                .find(|(entity, pos, name, _)| {
+                    *entity != item
+                        && pos.x == landing.x
+                        && pos.y == landing.y
+                        && name.name == item_name
+                })
+                .map(|(entity, _, _, _)| entity),
+            _ => None,
+        }
+    };
+    match pile {
+        Some(pile) => {
+            {
+                let mut quantities = ecs.write_storage::<Quantity>();
+                let amount = quantities.get(item).map_or(0, |stack| stack.amount);
+                if let Some(pile) = quantities.get_mut(pile) {
+                    pile.amount += amount;
+                }
+            }
+            ecs.delete_entity(item).expect("Unable to delete");
+        }
+        None => {
+            ecs.write_storage::<Position>()
+                .insert(
+                    item,
+                    Position {
+                        x: landing.x,
+                        y: landing.y,
+                    },
+                )
+                .expect("Unable to insert position");
+        }
+    }
+}
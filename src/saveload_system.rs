@@ -1,6 +1,6 @@
 use super::*;
+use serde::Deserialize;
 use specs::error::NoError;
-use specs::prelude::*;
 use specs::saveload::{
     DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
 };
@@ -8,6 +8,26 @@ use std::fs;
 use std::fs::File;
 use std::path::Path;
 
+const SAVE_FILE: &str = "./savegame.json";
+
+// Written ahead of the components. Bump it whenever the component lists below
+// or a saved struct change shape: components are read back in list order, so
+// a save from another version can't be loaded.
+const SAVE_VERSION: u32 = 2;
+
+fn save_version(data: &str) -> Option<u32> {
+    let mut de = serde_json::Deserializer::from_str(data);
+    u32::deserialize(&mut de).ok()
+}
+
+// True only for a save this build can read; older saves have no version.
+pub fn has_compatible_save() -> bool {
+    fs::read_to_string(SAVE_FILE)
+        .ok()
+        .and_then(|data| save_version(&data))
+        == Some(SAVE_VERSION)
+}
+
 macro_rules! serialize_individually {
     ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
         $(
@@ -39,9 +59,15 @@ macro_rules! deserialize_individually {
 pub fn save_game(ecs: &mut World) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let spawn_record = (*ecs.fetch::<super::spawner::SpawnRecord>()).clone();
+    let identification = (*ecs.fetch::<super::Identification>()).clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            spawn_record,
+            identification,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -52,8 +78,9 @@ pub fn save_game(ecs: &mut World) {
             ecs.read_storage::<SimpleMarker<SerializeMe>>(),
         );
 
-        let writer = File::create("./savegame.json").unwrap();
+        let writer = File::create(SAVE_FILE).unwrap();
         let mut serializer = serde_json::Serializer::new(writer);
+        SAVE_VERSION.serialize(&mut serializer).unwrap();
         serialize_individually!(
             ecs,
             serializer,
@@ -63,9 +90,20 @@ pub fn save_game(ecs: &mut World) {
             Player,
             Viewshed,
             Monster,
+            SeenPlayer,
+            MonsterAiState,
+            Morale,
+            Faction,
+            Stealth,
+            Group,
+            LootTable,
+            Abilities,
             Name,
             BlocksTile,
             CombatStats,
+            DamageDice,
+            Resistance,
+            Vulnerability,
             SufferDamage,
             WantsToMelee,
             Item,
@@ -76,11 +114,30 @@ pub fn save_game(ecs: &mut World) {
             Paralyze,
             ProvidesHealing,
             InBackpack,
+            InventorySlot,
             WantsToPickupItem,
             WantsToUseItem,
             DropItem,
             SerializationHelper,
-            Equippable
+            Equippable,
+            Equipped,
+            Launcher,
+            Ammunition,
+            WantsToThrow,
+            HungerClock,
+            ProvidesFood,
+            Identifies,
+            Charges,
+            Recharges,
+            Quantity,
+            Enchantment,
+            Cursed,
+            RemovesCurse,
+            Weight,
+            Value,
+            Gold,
+            Purse,
+            Merchant
         );
     }
 
@@ -100,8 +157,10 @@ pub fn load_game(ecs: &mut World) {
         }
     }
 
-    let data = fs::read_to_string("./savegame.json").unwrap();
+    let data = fs::read_to_string(SAVE_FILE).unwrap();
     let mut de = serde_json::Deserializer::from_str(&data);
+    let version = u32::deserialize(&mut de).unwrap();
+    assert_eq!(version, SAVE_VERSION, "Incompatible save version");
 
     {
         let mut d = (
@@ -119,9 +178,20 @@ pub fn load_game(ecs: &mut World) {
             Player,
             Viewshed,
             Monster,
+            SeenPlayer,
+            MonsterAiState,
+            Morale,
+            Faction,
+            Stealth,
+            Group,
+            LootTable,
+            Abilities,
             Name,
             BlocksTile,
             CombatStats,
+            DamageDice,
+            Resistance,
+            Vulnerability,
             SufferDamage,
             WantsToMelee,
             Item,
@@ -132,11 +202,30 @@ pub fn load_game(ecs: &mut World) {
             Paralyze,
             ProvidesHealing,
             InBackpack,
+            InventorySlot,
             WantsToPickupItem,
             WantsToUseItem,
             DropItem,
             SerializationHelper,
-            Equippable
+            Equippable,
+            Equipped,
+            Launcher,
+            Ammunition,
+            WantsToThrow,
+            HungerClock,
+            ProvidesFood,
+            Identifies,
+            Charges,
+            Recharges,
+            Quantity,
+            Enchantment,
+            Cursed,
+            RemovesCurse,
+            Weight,
+            Value,
+            Gold,
+            Purse,
+            Merchant
         );
     }
 
@@ -152,6 +241,8 @@ pub fn load_game(ecs: &mut World) {
             *worldmap = h.map.clone();
             worldmap.tile_content = vec![Vec::new(); super::map::MAPSIZE_COUNT];
             deleteme = Some(e);
+            *ecs.write_resource::<super::spawner::SpawnRecord>() = h.spawn_record.clone();
+            *ecs.write_resource::<super::Identification>() = h.identification.clone();
 
             for t in worldmap.tiles.iter_mut() {
                 t.set_background(&mut rng);
@@ -169,7 +260,7 @@ pub fn load_game(ecs: &mut World) {
 }
 
 pub fn delete_save() {
-    if Path::new("./savegame.json").exists() {
-        std::fs::remove_file("./savegame.json").expect("Unable to delete file");
+    if Path::new(SAVE_FILE).exists() {
+        std::fs::remove_file(SAVE_FILE).expect("Unable to delete file");
     }
 }
@@ -1,18 +1,63 @@
 use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 use super::{
-    map, map::Rect, random_table::RandomTable, AreaOfEffect, BlocksTile, CombatStats, Consumable,
-    DurationTurnHeal, EquipmentSlot, Equippable, GameImage, InflictsDamage, Item, Monster, Name,
-    Paralyze, Player, Position, Potion, ProvidesHealing, Ranged, Renderable, SeenPlayer,
-    SerializeMe, Viewshed, MAPSIZE_WIDTH,
+    map, map::Rect, random_table::RandomTable, Abilities, Ability, AiState, AmmoKind, Ammunition,
+    AreaOfEffect, BlocksTile, Charges, CombatStats, Consumable, Cursed, DamageDice, DamageType,
+    DurationTurnHeal, Enchantment, EquipmentSlot, Equippable, Equipped, Faction, GameImage, Gold,
+    Group, HungerClock, HungerState, Identifies, InBackpack, InflictsDamage, InventorySlot, Item,
+    Launcher, LootTable, Merchant, Monster, MonsterAiState, Morale, Name, Paralyze, Player,
+    Position, Potion, ProvidesFood, ProvidesHealing, Purse, Quantity, Ranged, Recharges,
+    RemovesCurse, Renderable, Resistance, SeenPlayer, SerializeMe, Stealth, Value, Viewshed,
+    Vulnerability, Weight, MAPSIZE_WIDTH, PLAYER_FACTION, WELL_FED_TURNS,
 };
 
 use specs::saveload::{MarkedBuilder, SimpleMarker};
 
 const MAX_MONSTERS: i32 = 4;
-const MAX_ITEMS: i32 = 2;
+// Chance (1 in n) that a monster starts the level asleep.
+const SLEEP_CHANCE: i32 = 3;
+const PACK_RADIUS: i32 = 2;
+// A shopkeeper starts with this many goods plus 1d4.
+const SHOP_STOCK: i32 = 4;
+
+const HEAL_POTION: &str = "Heal Potion";
+const MAGIC_MISSILE_SCROLL: &str = "Magic Missle Scroll";
+const FIREBALL_SCROLL: &str = "Fireball Scroll";
+const PARALYZE_SCROLL: &str = "Paralyze Scroll (麻痺の巻物) ";
+const IDENTIFY_SCROLL: &str = "Identify Scroll";
+const RECHARGE_SCROLL: &str = "Recharge Scroll";
+const REMOVE_CURSE_SCROLL: &str = "Remove Curse Scroll";
+// Types that start every run under a made-up name; see Identification.
+pub const UNIDENTIFIED_POTIONS: [&str; 1] = [HEAL_POTION];
+pub const UNIDENTIFIED_SCROLLS: [&str; 6] = [
+    MAGIC_MISSILE_SCROLL,
+    FIREBALL_SCROLL,
+    PARALYZE_SCROLL,
+    IDENTIFY_SCROLL,
+    RECHARGE_SCROLL,
+    REMOVE_CURSE_SCROLL,
+];
+// Chance (1 in n) that a piece of equipment is enchanted, and separately
+// that it is cursed.
+const ENCHANT_CHANCE: i32 = 3;
+const CURSE_CHANCE: i32 = 6;
+
+// Run-wide spawn bookkeeping, saved with the map.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SpawnRecord {
+    pub next_group_id: i32,
+    pub uniques: HashSet<String>,
+}
+
+impl SpawnRecord {
+    pub fn new_group(&mut self) -> i32 {
+        self.next_group_id += 1;
+        self.next_group_id
+    }
+}
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
     ecs.create_entity()
         .with(Position {
@@ -25,6 +70,11 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             background: None,
         })
         .with(Player {})
+        .with(Stealth { value: 30 })
+        .with(BlocksTile {})
+        .with(Faction {
+            name: PLAYER_FACTION.to_string(),
+        })
         .with(Viewshed {
             visible_tiles: Vec::new(),
             range: 8,
@@ -36,10 +86,18 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             defense: 2,
             power: 5,
         })
+        .with(DamageDice {
+            dice: "1d6".to_string(),
+        })
         .with(Name {
             name: "Player".to_string(),
         })
         .with(DurationTurnHeal { time: 0 })
+        .with(HungerClock {
+            state: HungerState::WellFed,
+            duration: WELL_FED_TURNS,
+        })
+        .with(Purse { gold: 0 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
@@ -47,37 +105,268 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
 fn room_table(map_depth: i32) -> RandomTable {
     RandomTable::new()
         .add("Kobold", 10)
+        .add("KoboldPack", map_depth)
         .add("Dragon", 1 + map_depth)
+        .add("GreatDragon", i32::max(0, map_depth - 2))
         .add("HealPotion", 5)
+        .add("Ration", 6)
         .add("FireballScroll", 2 + map_depth)
         .add("ParalyzeScroll", 2 + map_depth)
         .add("MagicMissileScroll", 4)
+        .add("IdentifyScroll", 3)
+        .add("RechargeScroll", 1 + map_depth / 2)
+        .add("RemoveCurseScroll", 2)
+        .add("WandOfMagicMissile", 2)
+        .add("WandOfParalysis", 1 + map_depth / 2)
+        .add("WandOfFireball", map_depth)
         .add("IronSword", 3)
         .add("IronShild", 3)
+        .add("ShortBow", 2)
+        .add("Sling", 2)
+        .add("Arrows", 3)
+        .add("SlingStones", 3)
+        .add("Gold", 6)
+}
+
+// What a shopkeeper stocks: the room table's goods without its monsters.
+fn shop_table(map_depth: i32) -> RandomTable {
+    RandomTable::new()
+        .add("HealPotion", 5)
+        .add("Ration", 5)
+        .add("FireballScroll", 1 + map_depth)
+        .add("ParalyzeScroll", 1 + map_depth)
+        .add("MagicMissileScroll", 3)
+        .add("IdentifyScroll", 4)
+        .add("RechargeScroll", 1 + map_depth / 2)
+        .add("RemoveCurseScroll", 3)
+        .add("WandOfMagicMissile", 2)
+        .add("WandOfParalysis", 1)
+        .add("WandOfFireball", map_depth / 2)
+        .add("IronSword", 3)
+        .add("IronShild", 3)
+        .add("ShortBow", 2)
+        .add("Sling", 2)
+        .add("Arrows", 3)
+        .add("SlingStones", 3)
 }
 
 fn dragon(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, GameImage::Dragon, "Dragoso", 24, 1, 5);
+    let dragon = monster(
+        ecs,
+        x,
+        y,
+        MonsterTemplate {
+            image: GameImage::Dragon,
+            name: "Dragoso",
+            faction: "Dragon",
+            hp: 24,
+            defence: 1,
+            power: 5,
+            damage: "1d8",
+            flee_percent: 10,
+        },
+    );
+    give_loot(ecs, dragon, "Dragon");
+    resist(ecs, dragon, vec![DamageType::Cold], vec![DamageType::Fire]);
+    let breath = breath(ecs, "Frost Breath", DamageType::Cold);
+    ecs.write_storage::<Abilities>()
+        .insert(
+            dragon,
+            Abilities {
+                abilities: vec![Ability {
+                    effect: breath,
+                    range: 5,
+                    cooldown: 8,
+                    ready_in: 0,
+                }],
+            },
+        )
+        .expect("Unable to insert abilities");
+}
+
+// Works like a fireball scroll, but is never picked up or used up.
+fn breath(ecs: &mut World, name: &str, kind: DamageType) -> Entity {
+    ecs.create_entity()
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Ranged { range: 5 })
+        .with(InflictsDamage { damage: 6, kind })
+        .with(AreaOfEffect { radius: 1 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
-fn kobold(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, GameImage::Kobold, "Kobolso", 16, 1, 4);
+pub fn great_dragon(ecs: &mut World, x: i32, y: i32) {
+    let dragon = monster(
+        ecs,
+        x,
+        y,
+        MonsterTemplate {
+            image: GameImage::Dragon,
+            name: "Great Dragoso",
+            faction: "Dragon",
+            hp: 60,
+            defence: 3,
+            power: 9,
+            damage: "2d6+2",
+            flee_percent: 0,
+        },
+    );
+    give_loot(ecs, dragon, "GreatDragon");
+    resist(
+        ecs,
+        dragon,
+        vec![DamageType::Fire, DamageType::Cold],
+        Vec::new(),
+    );
+    let breath = breath(ecs, "Fire Breath", DamageType::Fire);
+    ecs.write_storage::<Abilities>()
+        .insert(
+            dragon,
+            Abilities {
+                abilities: vec![Ability {
+                    effect: breath,
+                    range: 6,
+                    cooldown: 5,
+                    ready_in: 0,
+                }],
+            },
+        )
+        .expect("Unable to insert abilities");
 }
 
-fn monster<S: ToString>(
-    ecs: &mut World,
-    x: i32,
-    y: i32,
+// A leader plus 3-5 kobolds on free floor around it, all sharing a group id.
+fn kobold_pack(ecs: &mut World, room: &Rect, x: i32, y: i32, occupied: &mut HashSet<usize>) {
+    let group = ecs.write_resource::<SpawnRecord>().new_group();
+    let leader = monster(
+        ecs,
+        x,
+        y,
+        MonsterTemplate {
+            image: GameImage::Kobold,
+            name: "Kobolso Leader",
+            faction: "Kobold",
+            hp: 22,
+            defence: 2,
+            power: 5,
+            damage: "1d6",
+            flee_percent: 20,
+        },
+    );
+    give_loot(ecs, leader, "KoboldLeader");
+    let mut pack = vec![leader];
+
+    let mut free: Vec<(i32, i32)> = Vec::new();
+    for dy in -PACK_RADIUS..=PACK_RADIUS {
+        for dx in -PACK_RADIUS..=PACK_RADIUS {
+            let (mx, my) = (x + dx, y + dy);
+            let idx = (my as usize * MAPSIZE_WIDTH) + mx as usize;
+            if mx > room.x1
+                && mx <= room.x2
+                && my > room.y1
+                && my <= room.y2
+                && !occupied.contains(&idx)
+            {
+                free.push((mx, my));
+            }
+        }
+    }
+    let members = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 3)
+        + 2;
+    for _ in 0..members {
+        if free.is_empty() {
+            break;
+        }
+        let pick = (ecs
+            .write_resource::<RandomNumberGenerator>()
+            .roll_dice(1, free.len() as i32)
+            - 1) as usize;
+        let (mx, my) = free.remove(pick);
+        occupied.insert((my as usize * MAPSIZE_WIDTH) + mx as usize);
+        pack.push(kobold(ecs, mx, my));
+    }
+
+    let mut groups = ecs.write_storage::<Group>();
+    for member in pack {
+        groups
+            .insert(member, Group { id: group })
+            .expect("Unable to insert group");
+    }
+}
+
+pub fn kobold(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let kobold = monster(
+        ecs,
+        x,
+        y,
+        MonsterTemplate {
+            image: GameImage::Kobold,
+            name: "Kobolso",
+            faction: "Kobold",
+            hp: 16,
+            defence: 1,
+            power: 4,
+            damage: "1d4",
+            flee_percent: 30,
+        },
+    );
+    give_loot(ecs, kobold, "Kobold");
+    kobold
+}
+
+fn resist(ecs: &mut World, monster: Entity, resists: Vec<DamageType>, weak: Vec<DamageType>) {
+    if !resists.is_empty() {
+        ecs.write_storage::<Resistance>()
+            .insert(monster, Resistance { kinds: resists })
+            .expect("Unable to insert resistance");
+    }
+    if !weak.is_empty() {
+        ecs.write_storage::<Vulnerability>()
+            .insert(monster, Vulnerability { kinds: weak })
+            .expect("Unable to insert vulnerability");
+    }
+}
+
+fn give_loot(ecs: &mut World, monster: Entity, table: &str) {
+    ecs.write_storage::<LootTable>()
+        .insert(
+            monster,
+            LootTable {
+                table: table.to_string(),
+            },
+        )
+        .expect("Unable to insert loot table");
+}
+
+// The stats that set one kind of monster apart from another.
+struct MonsterTemplate<'a> {
     image: GameImage,
-    name: S,
+    name: &'a str,
+    faction: &'a str,
     hp: i32,
     defence: i32,
     power: i32,
-) {
+    damage: &'a str,
+    flee_percent: i32,
+}
+
+fn monster(ecs: &mut World, x: i32, y: i32, template: MonsterTemplate) -> Entity {
+    let initial_state = if ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, SLEEP_CHANCE)
+        == 1
+    {
+        AiState::Sleeping
+    } else {
+        AiState::Idle
+    };
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            image: image,
+            image: template.image,
             render_layer: 2,
             background: None,
         })
@@ -88,24 +377,34 @@ fn monster<S: ToString>(
         })
         .with(Monster {})
         .with(Name {
-            name: name.to_string(),
+            name: template.name.to_string(),
         })
         .with(BlocksTile {})
         .with(CombatStats {
-            max_hp: 16,
-            hp: hp,
-            defense: defence,
-            power: power,
+            max_hp: template.hp,
+            hp: template.hp,
+            defense: template.defence,
+            power: template.power,
+        })
+        .with(DamageDice {
+            dice: template.damage.to_string(),
         })
         .with(DurationTurnHeal { time: 0 })
         .with(SeenPlayer { point: None })
+        .with(MonsterAiState::new(initial_state))
+        .with(Morale {
+            flee_percent: template.flee_percent,
+        })
+        .with(Faction {
+            name: template.faction.to_string(),
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn iron_sword(ecs: &mut World, x: i32, y: i32) {
+fn iron_sword(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
-        .with(Position { x: x, y: y })
+        .with(Position { x, y })
         .with(Renderable {
             image: GameImage::Sword,
             render_layer: 1,
@@ -115,16 +414,21 @@ fn iron_sword(ecs: &mut World, x: i32, y: i32) {
             name: "Iron Sword".to_string(),
         })
         .with(Item {})
+        .with(Weight { amount: 30 })
+        .with(Value { price: 40 })
         .with(Equippable {
             slot: EquipmentSlot::Melee,
         })
+        .with(DamageDice {
+            dice: "1d8+1".to_string(),
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn iron_shild(ecs: &mut World, x: i32, y: i32) {
+fn iron_shild(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
-        .with(Position { x: x, y: y })
+        .with(Position { x, y })
         .with(Renderable {
             image: GameImage::Shield,
             render_layer: 1,
@@ -134,72 +438,177 @@ fn iron_shild(ecs: &mut World, x: i32, y: i32) {
             name: "Iron Shild".to_string(),
         })
         .with(Item {})
+        .with(Weight { amount: 40 })
+        .with(Value { price: 50 })
         .with(Equippable {
             slot: EquipmentSlot::Shield,
         })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
+fn launcher(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    ammo: AmmoKind,
+    range: i32,
+    damage: &str,
+) -> Entity {
     ecs.create_entity()
-        .with(Position { x: x, y: y })
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Bow,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { amount: 15 })
+        .with(Value { price: 35 })
+        .with(Equippable {
+            slot: EquipmentSlot::Ranged,
+        })
+        .with(Launcher { ammo, range })
+        .with(DamageDice {
+            dice: damage.to_string(),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn ammo(ecs: &mut World, kind: AmmoKind, count: i32, x: i32, y: i32) -> Entity {
+    let name = match kind {
+        AmmoKind::Arrow => "Arrow",
+        AmmoKind::Stone => "Sling Stone",
+    };
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Ammo,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Weight {
+            amount: match kind {
+                AmmoKind::Arrow => 1,
+                AmmoKind::Stone => 2,
+            },
+        })
+        .with(Value {
+            price: match kind {
+                AmmoKind::Arrow => 2,
+                AmmoKind::Stone => 1,
+            },
+        })
+        .with(Ammunition { kind })
+        .with(Quantity { amount: count })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
         .with(Renderable {
             image: GameImage::Potion,
             render_layer: 1,
             background: None,
         })
         .with(Name {
-            name: "Heal Potion".to_string(),
+            name: HEAL_POTION.to_string(),
         })
         .with(Item {})
+        .with(Weight { amount: 5 })
+        .with(Value { price: 20 })
         .with(Consumable {})
+        .with(Quantity { amount: 1 })
         .with(ProvidesHealing { heal_amount: 8 })
         .with(Potion { heal_amount: 8 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
+fn ration(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
-        .with(Position { x: x, y: y })
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Food,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: "Ration".to_string(),
+        })
+        .with(Item {})
+        .with(Weight { amount: 10 })
+        .with(Value { price: 8 })
+        .with(Consumable {})
+        .with(Quantity { amount: 1 })
+        .with(ProvidesFood {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
         .with(Renderable {
             image: GameImage::Scroll,
             render_layer: 1,
             background: None,
         })
         .with(Name {
-            name: "Magic Missle Scroll".to_string(),
+            name: MAGIC_MISSILE_SCROLL.to_string(),
         })
         .with(Item {})
+        .with(Weight { amount: 1 })
+        .with(Value { price: 25 })
         .with(Consumable {})
+        .with(Quantity { amount: 1 })
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
+        .with(InflictsDamage {
+            damage: 8,
+            kind: DamageType::Magic,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
+fn fireball_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
-        .with(Position { x: x, y: y })
+        .with(Position { x, y })
         .with(Renderable {
             image: GameImage::Scroll,
             render_layer: 1,
             background: None,
         })
         .with(Name {
-            name: "Fireball Scroll".to_string(),
+            name: FIREBALL_SCROLL.to_string(),
         })
         .with(Item {})
+        .with(Weight { amount: 1 })
+        .with(Value { price: 40 })
         .with(Consumable {})
+        .with(Quantity { amount: 1 })
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
+        .with(InflictsDamage {
+            damage: 8,
+            kind: DamageType::Fire,
+        })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn paralyze_scroll(ecs: &mut World, x: i32, y: i32) {
+fn paralyze_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -208,20 +617,328 @@ fn paralyze_scroll(ecs: &mut World, x: i32, y: i32) {
             background: None,
         })
         .with(Name {
-            name: "Paralyze Scroll (麻痺の巻物) ".to_string(),
+            name: PARALYZE_SCROLL.to_string(),
         })
         .with(Item {})
+        .with(Weight { amount: 1 })
+        .with(Value { price: 30 })
+        .with(Consumable {})
+        .with(Quantity { amount: 1 })
         .with(Paralyze { turns: 3 })
         .with(Ranged { range: 6 })
         .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn identify_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Scroll,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: IDENTIFY_SCROLL.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { amount: 1 })
+        .with(Value { price: 15 })
+        .with(Consumable {})
+        .with(Quantity { amount: 1 })
+        .with(Identifies {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn recharge_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Scroll,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: RECHARGE_SCROLL.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { amount: 1 })
+        .with(Value { price: 50 })
+        .with(Consumable {})
+        .with(Quantity { amount: 1 })
+        .with(Recharges {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn remove_curse_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Scroll,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: REMOVE_CURSE_SCROLL.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { amount: 1 })
+        .with(Value { price: 35 })
+        .with(Consumable {})
+        .with(Quantity { amount: 1 })
+        .with(RemovesCurse {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Deeper levels roll bigger bonuses. Cursed gear always has a penalty.
+fn enchant(ecs: &mut World, item: Entity, map_depth: i32) {
+    let level = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        if rng.roll_dice(1, CURSE_CHANCE) == 1 {
+            -rng.roll_dice(1, 1 + map_depth / 3)
+        } else if rng.roll_dice(1, ENCHANT_CHANCE) == 1 {
+            rng.roll_dice(1, 1 + map_depth / 2)
+        } else {
+            0
+        }
+    };
+    if level == 0 {
+        return;
+    }
+    ecs.write_storage::<Enchantment>()
+        .insert(
+            item,
+            Enchantment {
+                level,
+                known: false,
+            },
+        )
+        .expect("Unable to insert enchantment");
+    if level < 0 {
+        ecs.write_storage::<Cursed>()
+            .insert(item, Cursed {})
+            .expect("Unable to insert curse");
+    }
+}
+
+// A pile of coins, picked up by walking over it.
+fn gold(ecs: &mut World, amount: i32, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Gold,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: "Gold".to_string(),
+        })
+        .with(Gold { amount })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// A shopkeeper who stands still and trades when bumped.
+fn merchant(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Merchant,
+            render_layer: 2,
+            background: None,
+        })
+        .with(Name {
+            name: "Merchant".to_string(),
+        })
+        .with(Merchant {})
+        .with(BlocksTile {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Wands carry a few charges of the matching scroll's effect.
+fn wand<'a>(ecs: &'a mut World, x: i32, y: i32, name: &str) -> EntityBuilder<'a> {
+    let max = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 4)
+        + 2;
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            image: GameImage::Wand,
+            render_layer: 1,
+            background: None,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Weight { amount: 3 })
+        .with(Value { price: 80 })
+        .with(Charges { current: max, max })
+        .with(Ranged { range: 6 })
+}
+
+fn magic_missile_wand(ecs: &mut World, x: i32, y: i32) -> Entity {
+    wand(ecs, x, y, "Wand of Magic Missile")
+        .with(InflictsDamage {
+            damage: 8,
+            kind: DamageType::Magic,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn paralysis_wand(ecs: &mut World, x: i32, y: i32) -> Entity {
+    wand(ecs, x, y, "Wand of Paralysis")
+        .with(Paralyze { turns: 3 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn fireball_wand(ecs: &mut World, x: i32, y: i32) -> Entity {
+    wand(ecs, x, y, "Wand of Fireball")
+        .with(InflictsDamage {
+            damage: 8,
+            kind: DamageType::Fire,
+        })
+        .with(AreaOfEffect { radius: 3 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+macro_rules! copy_components {
+    ($ecs:expr, $from:expr, $to:expr, $( $type:ty ),*) => {
+        $(
+        {
+            let component = $ecs.read_storage::<$type>().get($from).cloned();
+            if let Some(component) = component {
+                $ecs.write_storage::<$type>()
+                    .insert($to, component)
+                    .expect("Unable to copy component");
+            }
+        }
+        )*
+    };
+}
+
+// Takes `amount` off a stack as a new entity that is in nobody's backpack
+// and nowhere on the map yet.
+pub fn split_stack(ecs: &mut World, item: Entity, amount: i32) -> Entity {
+    let piece = ecs
+        .create_entity()
+        .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    copy_components!(
+        ecs,
+        item,
+        piece,
+        Name,
+        Renderable,
+        Item,
+        Consumable,
+        Potion,
+        ProvidesHealing,
+        ProvidesFood,
+        Ranged,
+        InflictsDamage,
+        AreaOfEffect,
+        Paralyze,
+        Identifies,
+        Recharges,
+        RemovesCurse,
+        Ammunition,
+        DamageDice,
+        Weight,
+        Value
+    );
+    let mut quantities = ecs.write_storage::<Quantity>();
+    if let Some(stack) = quantities.get_mut(item) {
+        stack.amount -= amount;
+    }
+    quantities
+        .insert(piece, Quantity { amount })
+        .expect("Unable to insert quantity");
+    piece
+}
+
+// Puts an item into `owner`'s backpack the way picking it up would: onto a
+// stack of the same name, or else under the lowest free letter.
+pub fn stow(ecs: &mut World, item: Entity, owner: Entity) {
+    ecs.write_storage::<Position>().remove(item);
+    let stack = {
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let quantities = ecs.read_storage::<Quantity>();
+        match (quantities.get(item), names.get(item)) {
+            (Some(_), Some(stowed)) => (&entities, &backpack, &quantities, &names)
+                .join()
+                .find(|(entity, pack, _, name)| {
+                    *entity != item && pack.owner == owner && name.name == stowed.name
+                })
+                .map(|(entity, _, _, _)| entity),
+            _ => None,
+        }
+    };
+    if let Some(stack) = stack {
+        let mut quantities = ecs.write_storage::<Quantity>();
+        let amount = quantities.get(item).map_or(0, |stowed| stowed.amount);
+        if let Some(stack) = quantities.get_mut(stack) {
+            stack.amount += amount;
+        }
+        ecs.entities().delete(item).expect("Delete failed");
+        return;
+    }
+
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let mut slots = ecs.write_storage::<InventorySlot>();
+    let used_slots: Vec<i32> = (&backpack, &slots)
+        .join()
+        .filter(|(pack, _)| pack.owner == owner)
+        .map(|(_, slot)| slot.slot)
+        .collect();
+    let mut slot = 0;
+    while used_slots.contains(&slot) {
+        slot += 1;
+    }
+    slots
+        .insert(item, InventorySlot { slot })
+        .expect("Unable to insert inventory slot");
+    backpack
+        .insert(item, InBackpack { owner })
+        .expect("Unable to insert backpack entry");
 }
 
-fn room_inside_idx(room: &map::Rect, ecs: &mut World) -> usize {
-    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-    let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-    let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-    (y * map::MAPSIZE_WIDTH) + x
+// Fills every room but the first, where the player arrives: shops get a
+// merchant, the rest monsters and loot unless the level is a town.
+pub fn spawn_level(ecs: &mut World, map: &map::Map) {
+    for (i, room) in map.rooms.iter().enumerate().skip(1) {
+        if map.shops.contains(&i) {
+            spawn_shop(ecs, room, map.depth);
+        } else if !map.town {
+            spawn_room(ecs, room, map.depth);
+        }
+    }
+}
+
+fn spawn_shop(ecs: &mut World, room: &Rect, map_depth: i32) {
+    let (x, y) = room.center();
+    let keeper = merchant(ecs, x, y);
+    let stock_table = shop_table(map_depth);
+    let stock_count = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 4)
+        + SHOP_STOCK;
+    for _ in 0..stock_count {
+        let roll = stock_table.roll(&mut ecs.write_resource::<RandomNumberGenerator>());
+        if let Some(item) = spawn_item(ecs, &roll, x, y, map_depth) {
+            stow(ecs, item, keeper);
+        }
+    }
 }
 
 pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
@@ -237,8 +954,8 @@ pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
                 let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
                 let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
                 let idx = (y * MAPSIZE_WIDTH) + x;
-                if !spawn_points.contains_key(&idx) {
-                    spawn_points.insert(idx as usize, spawn_table.roll(&mut rng));
+                if let Entry::Vacant(e) = spawn_points.entry(idx) {
+                    e.insert(spawn_table.roll(&mut rng));
                     added = true;
                 } else {
                     tries += 1;
@@ -247,19 +964,181 @@ pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
         }
     }
 
+    let mut occupied: HashSet<usize> = spawn_points.keys().cloned().collect();
     for spawn in spawn_points.iter() {
         let x = (*spawn.0 % MAPSIZE_WIDTH) as i32;
         let y = (*spawn.0 / MAPSIZE_WIDTH) as i32;
         match spawn.1.as_ref() {
-            "Kobold" => kobold(ecs, x, y),
+            "Kobold" => {
+                kobold(ecs, x, y);
+            }
+            "KoboldPack" => kobold_pack(ecs, room, x, y, &mut occupied),
             "Dragon" => dragon(ecs, x, y),
-            "HealPotion" => health_potion(ecs, x, y),
-            "FireBallScroll" => fireball_scroll(ecs, x, y),
-            "MagicMissileScroll" => magic_missile_scroll(ecs, x, y),
-            "ParalyzeScroll" => paralyze_scroll(ecs, x, y),
-            "IronSword" => iron_sword(ecs, x, y),
-            "IronShild" => iron_shild(ecs, x, y),
-            _ => {}
+            "GreatDragon" => {
+                if ecs
+                    .write_resource::<SpawnRecord>()
+                    .uniques
+                    .insert("GreatDragon".to_string())
+                {
+                    great_dragon(ecs, x, y);
+                }
+            }
+            item => {
+                spawn_item(ecs, item, x, y, map_depth);
+            }
+        }
+    }
+}
+
+fn spawn_item(ecs: &mut World, name: &str, x: i32, y: i32, map_depth: i32) -> Option<Entity> {
+    let item = match name {
+        "HealPotion" => Some(health_potion(ecs, x, y)),
+        "Ration" => Some(ration(ecs, x, y)),
+        "FireballScroll" => Some(fireball_scroll(ecs, x, y)),
+        "MagicMissileScroll" => Some(magic_missile_scroll(ecs, x, y)),
+        "ParalyzeScroll" => Some(paralyze_scroll(ecs, x, y)),
+        "IdentifyScroll" => Some(identify_scroll(ecs, x, y)),
+        "RechargeScroll" => Some(recharge_scroll(ecs, x, y)),
+        "RemoveCurseScroll" => Some(remove_curse_scroll(ecs, x, y)),
+        "WandOfMagicMissile" => Some(magic_missile_wand(ecs, x, y)),
+        "WandOfParalysis" => Some(paralysis_wand(ecs, x, y)),
+        "WandOfFireball" => Some(fireball_wand(ecs, x, y)),
+        "IronSword" => Some(iron_sword(ecs, x, y)),
+        "IronShild" => Some(iron_shild(ecs, x, y)),
+        "ShortBow" => Some(launcher(ecs, x, y, "Short Bow", AmmoKind::Arrow, 8, "1d6")),
+        "Sling" => Some(launcher(ecs, x, y, "Sling", AmmoKind::Stone, 6, "1d4")),
+        "Arrows" | "SlingStones" => {
+            let kind = if name == "Arrows" {
+                AmmoKind::Arrow
+            } else {
+                AmmoKind::Stone
+            };
+            let count = ecs
+                .write_resource::<RandomNumberGenerator>()
+                .roll_dice(2, 6);
+            Some(ammo(ecs, kind, count, x, y))
+        }
+        "Gold" => {
+            let amount = ecs
+                .write_resource::<RandomNumberGenerator>()
+                .roll_dice(1 + map_depth, 6);
+            Some(gold(ecs, amount, x, y))
+        }
+        _ => None,
+    };
+    if let Some(item) = item {
+        if ecs.read_storage::<Equippable>().get(item).is_some() {
+            enchant(ecs, item, map_depth);
         }
     }
+    item
+}
+
+fn loot_table(table: &str) -> RandomTable {
+    match table {
+        "Kobold" => RandomTable::new()
+            .add("None", 6)
+            .add("Gold", 3)
+            .add("HealPotion", 3)
+            .add("MagicMissileScroll", 1)
+            .add("SlingStones", 1),
+        "Dragon" => RandomTable::new()
+            .add("None", 2)
+            .add("Gold", 3)
+            .add("HealPotion", 2)
+            .add("FireballScroll", 2)
+            .add("IronSword", 1),
+        "KoboldLeader" => RandomTable::new()
+            .add("None", 2)
+            .add("Ration", 2)
+            .add("HealPotion", 2)
+            .add("ParalyzeScroll", 1)
+            .add("WandOfMagicMissile", 1)
+            .add("IronShild", 1),
+        "GreatDragon" => RandomTable::new()
+            .add("FireballScroll", 1)
+            .add("IronSword", 1)
+            .add("IronShild", 1),
+        _ => RandomTable::new(),
+    }
+}
+
+// Leaves whatever the victim carried plus a roll on its loot table where it
+// died, and returns what ended up on the floor.
+pub fn drop_loot(ecs: &mut World, victim: Entity) -> Vec<Entity> {
+    let mut dropped: Vec<Entity> = Vec::new();
+    let pos = match ecs.read_storage::<Position>().get(victim) {
+        Some(pos) => *pos,
+        None => return dropped,
+    };
+
+    {
+        let entities = ecs.entities();
+        let mut positions = ecs.write_storage::<Position>();
+        let mut backpack = ecs.write_storage::<InBackpack>();
+        let mut equipped = ecs.write_storage::<Equipped>();
+        let mut slots = ecs.write_storage::<InventorySlot>();
+        for item in entities.join() {
+            let carried = backpack.get(item).is_some_and(|b| b.owner == victim)
+                || equipped.get(item).is_some_and(|e| e.owner == victim);
+            if carried {
+                backpack.remove(item);
+                equipped.remove(item);
+                slots.remove(item);
+                positions
+                    .insert(item, pos)
+                    .expect("Unable to insert position");
+                dropped.push(item);
+            }
+        }
+    }
+
+    let table = ecs
+        .read_storage::<LootTable>()
+        .get(victim)
+        .map(|loot| loot.table.clone());
+    if let Some(table) = table {
+        let roll = loot_table(&table).roll(&mut ecs.write_resource::<RandomNumberGenerator>());
+        let map_depth = ecs.fetch::<map::Map>().depth;
+        if let Some(item) = spawn_item(ecs, &roll, pos.x, pos.y, map_depth) {
+            dropped.push(item);
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        ecs.insert(RandomNumberGenerator::seeded(1));
+        ecs.insert(SpawnRecord::default());
+        ecs
+    }
+
+    fn at_full_health(ecs: &World) -> bool {
+        let stats = ecs.read_storage::<CombatStats>();
+        let monsters = ecs.read_storage::<Monster>();
+        (&stats, &monsters).join().all(|(s, _)| s.hp == s.max_hp)
+    }
+
+    #[test]
+    fn dragoso_starts_at_full_health() {
+        let mut ecs = world();
+        dragon(&mut ecs, 1, 1);
+        assert!(at_full_health(&ecs));
+    }
+
+    #[test]
+    fn every_monster_starts_at_full_health() {
+        let mut ecs = world();
+        great_dragon(&mut ecs, 1, 1);
+        kobold(&mut ecs, 2, 2);
+        let room = Rect::new(0, 0, 8, 8);
+        kobold_pack(&mut ecs, &room, 4, 4, &mut HashSet::new());
+        assert!(at_full_health(&ecs));
+    }
 }
@@ -0,0 +1,167 @@
+use super::{
+    backpack_page_count, carried_weight, carry_limit, spawner, CombatStats, Enchantment, Equipped,
+    GameLog, Identification, InBackpack, InventorySlot, LogEvent, Name, Noun, Purse, Quantity,
+    RunState, Value, Weight,
+};
+use specs::prelude::*;
+
+// Each point of enchantment, good or bad, moves an item's price this much
+// once it is known; until then the item sells like a plain one.
+const ENCHANTMENT_PRICE: i32 = 10;
+
+// What a merchant asks for one of an item, or None if they don't deal in it.
+pub fn buy_price(
+    values: &ReadStorage<Value>,
+    enchantments: &ReadStorage<Enchantment>,
+    item: Entity,
+) -> Option<i32> {
+    let value = values.get(item)?;
+    let level = enchantments
+        .get(item)
+        .filter(|e| e.known)
+        .map_or(0, |e| e.level);
+    Some(i32::max(1, value.price + level * ENCHANTMENT_PRICE))
+}
+
+pub fn sell_price(
+    values: &ReadStorage<Value>,
+    enchantments: &ReadStorage<Enchantment>,
+    item: Entity,
+) -> Option<i32> {
+    buy_price(values, enchantments, item).map(|price| i32::max(1, price / 2))
+}
+
+// Pages in the trade window: enough for whichever side has more to offer.
+pub fn trade_page_count(ecs: &World, merchant: Entity) -> i32 {
+    let player_entity = *ecs.fetch::<Entity>();
+    i32::max(
+        backpack_page_count(ecs, merchant),
+        backpack_page_count(ecs, player_entity),
+    )
+}
+
+fn item_in_slot(ecs: &World, owner: Entity, slot: i32) -> Option<Entity> {
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let slots = ecs.read_storage::<InventorySlot>();
+    (&entities, &backpack, &slots)
+        .join()
+        .find(|(_, pack, s)| pack.owner == owner && s.slot == slot)
+        .map(|(entity, _, _)| entity)
+}
+
+// Takes one off a stack to trade, or the item itself if it is alone.
+fn take_one(ecs: &mut World, item: Entity) -> Entity {
+    let stacked = ecs
+        .read_storage::<Quantity>()
+        .get(item)
+        .is_some_and(|stack| stack.amount > 1);
+    if stacked {
+        spawner::split_stack(ecs, item, 1)
+    } else {
+        item
+    }
+}
+
+fn trade_noun(ecs: &World, item: Entity) -> Noun {
+    Noun::item(
+        &ecs.read_storage::<Name>(),
+        &ecs.fetch::<Identification>(),
+        &ecs.read_storage::<Enchantment>(),
+        item,
+    )
+}
+
+// Buys one of whatever the merchant keeps under `slot`. Trading takes no time.
+pub fn try_buy(ecs: &mut World, merchant: Entity, slot: i32) -> RunState {
+    let trading = RunState::ShowTrade { merchant };
+    let item = match item_in_slot(ecs, merchant, slot) {
+        Some(item) => item,
+        None => return trading,
+    };
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = match buy_price(
+        &ecs.read_storage::<Value>(),
+        &ecs.read_storage::<Enchantment>(),
+        item,
+    ) {
+        Some(price) => price,
+        None => return trading,
+    };
+
+    let gold = ecs
+        .read_storage::<Purse>()
+        .get(player_entity)
+        .map_or(0, |purse| purse.gold);
+    if gold < price {
+        let item = trade_noun(ecs, item);
+        ecs.fetch_mut::<GameLog>()
+            .push(LogEvent::NotEnoughGold { item });
+        return trading;
+    }
+
+    let too_heavy = {
+        let weights = ecs.read_storage::<Weight>();
+        let carried = carried_weight(
+            &ecs.entities(),
+            &ecs.read_storage::<InBackpack>(),
+            &weights,
+            &ecs.read_storage::<Quantity>(),
+            player_entity,
+        );
+        let one = weights.get(item).map_or(0, |weight| weight.amount);
+        ecs.read_storage::<CombatStats>()
+            .get(player_entity)
+            .is_some_and(|stats| carried + one > carry_limit(stats))
+    };
+    if too_heavy {
+        let item = trade_noun(ecs, item);
+        ecs.fetch_mut::<GameLog>().push(LogEvent::TooHeavy { item });
+        return trading;
+    }
+
+    let bought = take_one(ecs, item);
+    if let Some(purse) = ecs.write_storage::<Purse>().get_mut(player_entity) {
+        purse.gold -= price;
+    }
+    let item = trade_noun(ecs, bought);
+    ecs.fetch_mut::<GameLog>()
+        .push(LogEvent::Buy { item, price });
+    spawner::stow(ecs, bought, player_entity);
+    trading
+}
+
+// Sells one of whatever the player keeps under `slot`. Worn things have to
+// come off first.
+pub fn try_sell(ecs: &mut World, merchant: Entity, slot: i32) -> RunState {
+    let trading = RunState::ShowTrade { merchant };
+    let player_entity = *ecs.fetch::<Entity>();
+    let item = match item_in_slot(ecs, player_entity, slot) {
+        Some(item) => item,
+        None => return trading,
+    };
+    if ecs.read_storage::<Equipped>().get(item).is_some() {
+        let item = trade_noun(ecs, item);
+        ecs.fetch_mut::<GameLog>()
+            .push(LogEvent::SellEquipped { item });
+        return trading;
+    }
+    let price = match sell_price(
+        &ecs.read_storage::<Value>(),
+        &ecs.read_storage::<Enchantment>(),
+        item,
+    ) {
+        Some(price) => price,
+        None => return trading,
+    };
+
+    let sold = take_one(ecs, item);
+    if let Some(purse) = ecs.write_storage::<Purse>().get_mut(player_entity) {
+        purse.gold += price;
+    }
+    let item = trade_noun(ecs, sold);
+    ecs.fetch_mut::<GameLog>()
+        .push(LogEvent::Sell { item, price });
+    spawner::stow(ecs, sold, merchant);
+    trading
+}
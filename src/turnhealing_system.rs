@@ -1,4 +1,4 @@
-use super::{CombatStats, DurationTurnHeal};
+use super::{CombatStats, DurationTurnHeal, HungerClock, HungerState};
 use specs::prelude::*;
 use std::cmp::min;
 
@@ -14,11 +14,19 @@ impl<'a> System<'a> for TurnHealing {
         Entities<'a>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, DurationTurnHeal>,
+        ReadStorage<'a, HungerClock>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut combat_stats, mut turnheals) = data;
-        for (_entity, stats, turnheal) in (&entities, &mut combat_stats, &mut turnheals).join() {
+        let (entities, mut combat_stats, mut turnheals, clocks) = data;
+        for (entity, stats, turnheal) in (&entities, &mut combat_stats, &mut turnheals).join() {
+            // A starving body doesn't mend.
+            if clocks
+                .get(entity)
+                .is_some_and(|clock| clock.state == HungerState::Starving)
+            {
+                continue;
+            }
             turnheal.time += 1;
             if turnheal.time >= DURATION_TURNHEAL && stats.hp > 0 {
                 stats.hp = min(stats.max_hp, stats.hp + turn_heal_amount());
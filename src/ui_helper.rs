@@ -1,7 +1,8 @@
 use super::{
-    gamelog, point_to_left, point_to_top, CombatStats, InBackpack, Map, Name, Player, Point,
-    Position, RenderMode, Renderable, RunState, State, Viewshed, WantsToUseItem, TILESIZE,
-    WINDOWSIZE_HEIGHT,
+    gamelog, inventory_page_count, item_name, player_load, point_to_left, point_to_top, ui_text,
+    Charges, Enchantment, Identification, InBackpack, InventorySlot, Locale, Map, Monster, Name,
+    Point, Position, Quantity, RenderMode, Renderable, RunState, State, TargetAction, UiText,
+    Viewshed, WantsToThrow, WantsToUseItem, TILESIZE, WINDOWSIZE_HEIGHT,
 };
 use ggez::graphics;
 use ggez::graphics::*;
@@ -63,8 +64,35 @@ pub fn draw_tile_text(
     .expect("Cannot draw Text");
 }
 
+// A small "z" in the top right corner of a sleeping monster's tile.
+pub fn draw_sleep_marker(ctx: &mut Context, tile_pos: Point2<f32>, font: graphics::Font) {
+    let mut text = graphics::Text::default();
+    text.add(
+        TextFragment::new("z")
+            .color(Color::new(0.5, 0.5, 1.0, 1.0))
+            .font(font)
+            .scale(Scale {
+                x: (TILESIZE / 2) as f32,
+                y: (TILESIZE / 2) as f32,
+            }),
+    );
+
+    graphics::queue_text(ctx, &text, Point2 { x: 0.0, y: 0.0 }, None);
+    graphics::draw_queued_text(
+        ctx,
+        graphics::DrawParam::default().dest(Point2 {
+            x: tile_pos.x + (TILESIZE / 2) as f32,
+            y: tile_pos.y,
+        }),
+        None,
+        graphics::FilterMode::Linear,
+    )
+    .expect("Cannot draw sleep marker");
+}
+
 pub fn draw_message_window(ctx: &mut Context, ecs: &World, font: Font) {
     let log = ecs.fetch::<gamelog::GameLog>();
+    let locale = *ecs.fetch::<Locale>();
 
     let windowpoint_x = map_to_p(1);
     let window_height = map_to_p(2);
@@ -96,7 +124,7 @@ pub fn draw_message_window(ctx: &mut Context, ecs: &World, font: Font) {
     let max_message_size = window_height / 16.0;
     for (i, s) in log.entries.iter().rev().enumerate() {
         let mut fix_s: Vec<TextFragment> = Vec::new();
-        for t in s {
+        for t in s.display_fragments(locale).iter() {
             let fix_i = (i as f32) * 0.3;
             match t.color {
                 None => fix_s.push(t.clone().color(Color::new(1.0, 1.0, 1.0, 1.0 - fix_i))),
@@ -126,23 +154,34 @@ pub fn draw_message_window(ctx: &mut Context, ecs: &World, font: Font) {
     }
 }
 
+#[allow(dead_code)]
 pub fn draw_inventory_window(
     state: &State,
     ctx: &mut Context,
     ecs: &World,
     font: Font,
-    mouse_pos: Point2<f32>,
     render_mode: &RenderMode,
 ) {
     let player_entity = ecs.fetch::<Entity>();
     let names = ecs.read_storage::<Name>();
+    let identification = ecs.fetch::<Identification>();
     let backpack = ecs.read_storage::<InBackpack>();
     let renderables = ecs.read_storage::<Renderable>();
-
-    let inventory_len = (&backpack, &names, &renderables)
+    let slots = ecs.read_storage::<InventorySlot>();
+    let charges = ecs.read_storage::<Charges>();
+    let quantities = ecs.read_storage::<Quantity>();
+    let enchantments = ecs.read_storage::<Enchantment>();
+    let locale = *ecs.fetch::<Locale>();
+    let (carried, capacity) = player_load(ecs);
+    let page_count = inventory_page_count(ecs);
+    let inventory_page = state.imgui.inventory_page;
+
+    let mut items = (&ecs.entities(), &backpack, &names, &renderables, &slots)
         .join()
-        .filter(|item| item.0.owner == *player_entity)
-        .count();
+        .filter(|item| item.1.owner == *player_entity && item.4.page() == inventory_page)
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| item.4.slot);
+    let inventory_len = items.len();
     let start_window_x = map_to_p(1);
     let start_window_y = map_to_p(1);
     let item_image_x = 4.0 * 16.0;
@@ -171,7 +210,14 @@ pub fn draw_inventory_window(
 
     draw_text(
         ctx,
-        &vec![TextFragment::new("所持品(inventory)")],
+        &vec![TextFragment::new(format!(
+            "{} [{}/{}] ({}/{})",
+            ui_text(UiText::Inventory, locale),
+            carried,
+            capacity,
+            inventory_page + 1,
+            page_count
+        ))],
         Point2 {
             x: start_window_x + map_to_p(1),
             y: start_window_y + 16.0,
@@ -179,45 +225,49 @@ pub fn draw_inventory_window(
         font,
     );
 
-    let mut j = 0;
-    for (_pack, name, use_image) in (&backpack, &names, &renderables)
-        .join()
-        .filter(|item| item.0.owner == *player_entity)
-    {
-        let line_y = start_window_y + map_to_p(2) + map_to_p(j);
+    for (j, (entity, _pack, name, use_image, slot)) in items.iter().enumerate() {
+        let look = identification.appearance(&name.name);
+        let line_y = start_window_y + map_to_p(2) + map_to_p(j as i32);
 
         draw_text(
             ctx,
-            &vec![TextFragment::new(format!("({})", ((97 + j) as u8) as char))],
+            &vec![TextFragment::new(format!("({})", slot.letter()))],
             Point2 {
                 x: start_window_x + map_to_p(1),
                 y: line_y,
             },
             font,
         );
-        match render_mode {
-            RenderMode::Tile => {
+        let image = match render_mode {
+            RenderMode::Tile => state.images.get(&use_image.image),
+            RenderMode::Unicode => None,
+        };
+        match image {
+            Some(image) => {
                 graphics::draw(
                     ctx,
-                    state.images.get(&use_image.image).unwrap(),
-                    graphics::DrawParam::default().dest(Point2 {
-                        x: start_window_x + item_image_x,
-                        y: line_y - 8.0,
-                    }),
+                    image,
+                    graphics::DrawParam::default()
+                        .dest(Point2 {
+                            x: start_window_x + item_image_x,
+                            y: line_y - 8.0,
+                        })
+                        .color(look.map_or(graphics::WHITE, |look| look.color())),
                 )
                 .expect("Cannot draw Item image.");
             }
-            RenderMode::Unicode => {
+            None => {
                 let mut text = graphics::Text::default();
                 text.add(
-                    state
-                        .enum_to_unicode(&use_image)
-                        .clone()
-                        .font(font)
-                        .scale(Scale {
-                            x: TILESIZE as f32,
-                            y: TILESIZE as f32,
-                        }),
+                    look.map_or_else(
+                        || state.enum_to_unicode(use_image),
+                        |look| look.text_fragment(),
+                    )
+                    .font(font)
+                    .scale(Scale {
+                        x: TILESIZE as f32,
+                        y: TILESIZE as f32,
+                    }),
                 );
 
                 graphics::queue_text(ctx, &text, Point2 { x: 0.0, y: 0.0 }, None);
@@ -234,16 +284,27 @@ pub fn draw_inventory_window(
             }
         }
 
+        let mut label = format!(
+            " - {}",
+            item_name(&names, &identification, &enchantments, *entity)
+        );
+        if let Some(stack) = quantities.get(*entity) {
+            if stack.amount > 1 {
+                label = format!("{} x{}", label, stack.amount);
+            }
+        }
+        if let Some(charge) = charges.get(*entity) {
+            label = format!("{} ({}/{})", label, charge.current, charge.max);
+        }
         draw_text(
             ctx,
-            &vec![TextFragment::new(format!(" - {}", &name.name))],
+            &vec![TextFragment::new(label)],
             Point2 {
                 x: start_window_x + map_to_p(3),
                 y: line_y,
             },
             font,
         );
-        j += 1;
     }
 }
 
@@ -252,8 +313,8 @@ pub fn draw_mouse_pos(ctx: &mut Context, x: f32, y: f32) {
         ctx,
         DrawMode::stroke(2.0),
         Rect::new(
-            map_to_p(p_to_map(x)) as f32,
-            map_to_p(p_to_map(y)) as f32,
+            map_to_p(p_to_map(x)),
+            map_to_p(p_to_map(y)),
             TILESIZE as f32,
             TILESIZE as f32,
         ),
@@ -285,19 +346,12 @@ pub fn draw_object_focus_rect(ctx: &mut Context, ecs: &World, x: f32, y: f32, po
     }
 }
 
-pub fn draw_aoe_radius(ctx: &mut Context, ecs: &World, x: f32, y: f32, radius: i32) {
+pub fn draw_aoe_radius(ctx: &mut Context, ecs: &World, target: Point, radius: i32) {
     let map = ecs.fetch::<Map>();
     let player_pos = ecs.fetch::<Point>();
     let fix_player_pos_left = point_to_left(*player_pos) + 1;
     let fix_player_pos_top = point_to_top(*player_pos) + 1;
-    let blast_tiles = bracket_lib::prelude::field_of_view(
-        Point {
-            x: p_to_map(x) + fix_player_pos_left,
-            y: p_to_map(y) + fix_player_pos_top,
-        },
-        radius,
-        &*map,
-    );
+    let blast_tiles = bracket_lib::prelude::field_of_view(target, radius, &*map);
     for tile_idx in blast_tiles.iter() {
         let r = Mesh::new_rectangle(
             ctx,
@@ -315,6 +369,25 @@ pub fn draw_aoe_radius(ctx: &mut Context, ecs: &World, x: f32, y: f32, radius: i
     }
 }
 
+pub fn draw_target_cursor(ctx: &mut Context, ecs: &World, target: Point) {
+    let player_pos = ecs.fetch::<Point>();
+    let fix_player_pos_left = point_to_left(*player_pos) + 1;
+    let fix_player_pos_top = point_to_top(*player_pos) + 1;
+    let r = Mesh::new_rectangle(
+        ctx,
+        DrawMode::stroke(3.0),
+        Rect::new(
+            map_to_p(target.x - fix_player_pos_left),
+            map_to_p(target.y - fix_player_pos_top),
+            TILESIZE as f32,
+            TILESIZE as f32,
+        ),
+        Color::new(1.0, 0.0, 0.0, 1.0),
+    )
+    .unwrap();
+    graphics::draw(ctx, &r, graphics::DrawParam::default()).unwrap();
+}
+
 pub fn draw_ranged_target(ecs: &World, ctx: &mut Context, range: i32) {
     let available_cells = inside_range(ecs, range);
     let player_pos = ecs.fetch::<Point>();
@@ -323,34 +396,23 @@ pub fn draw_ranged_target(ecs: &World, ctx: &mut Context, range: i32) {
 
     for idx in available_cells.iter() {
         let distance = bracket_lib::prelude::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
-        let r: Mesh;
-        if distance <= range as f32 {
-            r = Mesh::new_rectangle(
-                ctx,
-                DrawMode::fill(),
-                Rect::new(
-                    map_to_p(idx.x - fix_player_pos_left),
-                    map_to_p(idx.y - fix_player_pos_top),
-                    TILESIZE as f32,
-                    TILESIZE as f32,
-                ),
-                Color::new(1.0, 1.0, 0.0, 0.1),
-            )
-            .unwrap();
+        let color = if distance <= range as f32 {
+            Color::new(1.0, 1.0, 0.0, 0.1)
         } else {
-            r = Mesh::new_rectangle(
-                ctx,
-                DrawMode::fill(),
-                Rect::new(
-                    map_to_p(idx.x - fix_player_pos_left),
-                    map_to_p(idx.y - fix_player_pos_top),
-                    TILESIZE as f32,
-                    TILESIZE as f32,
-                ),
-                Color::new(1.0, 0.0, 0.0, 0.1),
-            )
-            .unwrap();
-        }
+            Color::new(1.0, 0.0, 0.0, 0.1)
+        };
+        let r = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(
+                map_to_p(idx.x - fix_player_pos_left),
+                map_to_p(idx.y - fix_player_pos_top),
+                TILESIZE as f32,
+                TILESIZE as f32,
+            ),
+            color,
+        )
+        .unwrap();
         graphics::draw(ctx, &r, graphics::DrawParam::default()).unwrap();
     }
 }
@@ -364,6 +426,8 @@ pub fn draw_tooltip_with_mouse_motion(
 ) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
+    let identification = ecs.fetch::<Identification>();
+    let enchantments = ecs.read_storage::<Enchantment>();
     let positions = ecs.read_storage::<Position>();
     let mouse_pos_x = p_to_map(x);
     let mouse_pos_y = p_to_map(y);
@@ -378,11 +442,22 @@ pub fn draw_tooltip_with_mouse_motion(
         return;
     }
 
-    for (name, position) in (&names, &positions).join() {
+    let target_x = mouse_pos_x + fix_player_pos_left;
+    let target_y = mouse_pos_y + fix_player_pos_top;
+    if target_x < 0
+        || target_y < 0
+        || target_x >= map.width
+        || target_y >= map.height
+        || !map.visible_tiles[map.xy_idx(target_x, target_y)]
+    {
+        return;
+    }
+
+    for (entity, _name, position) in (&ecs.entities(), &names, &positions).join() {
         if position.x == mouse_pos_x + fix_player_pos_left
             && position.y == mouse_pos_y + fix_player_pos_top
         {
-            tooltip.push(name.name.to_string());
+            tooltip.push(item_name(&names, &identification, &enchantments, entity));
         }
     }
 
@@ -418,7 +493,15 @@ pub fn fix_p_to_bottom(ph: f32) -> f32 {
     map_to_p(WINDOWSIZE_HEIGHT) - (ph + 16.0)
 }
 
-fn inside_range(ecs: &World, range: i32) -> Vec<Point> {
+pub fn mouse_to_map_point(ecs: &World, x: f32, y: f32) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    Point::new(
+        p_to_map(x) + point_to_left(*player_pos) + 1,
+        p_to_map(y) + point_to_top(*player_pos) + 1,
+    )
+}
+
+pub fn inside_range(ecs: &World, range: i32) -> Vec<Point> {
     let player_entity = ecs.fetch::<Entity>();
     let player_pos = ecs.fetch::<Point>();
 
@@ -439,31 +522,101 @@ fn inside_range(ecs: &World, range: i32) -> Vec<Point> {
     available_cells
 }
 
-pub fn try_target_object(ecs: &World, x: f32, y: f32, item: Entity, range: i32) -> RunState {
+// Hostiles the player can see inside the range, nearest first.
+pub fn visible_targets(ecs: &World, range: i32) -> Vec<(Entity, Point)> {
     let player_pos = ecs.fetch::<Point>();
-    let mouse_pos_x = p_to_map(x);
-    let mouse_pos_y = p_to_map(y);
-    let fix_player_pos_left = point_to_left(*player_pos) + 1;
-    let fix_player_pos_top = point_to_top(*player_pos) + 1;
+    let entities = ecs.entities();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let available_cells = inside_range(ecs, range);
 
+    let mut targets = (&entities, &monsters, &positions)
+        .join()
+        .map(|(entity, _monster, pos)| (entity, Point::new(pos.x, pos.y)))
+        .filter(|target| available_cells.contains(&target.1))
+        .collect::<Vec<_>>();
+    targets.sort_by(|a, b| {
+        let distance_a = bracket_lib::prelude::DistanceAlg::Pythagoras.distance2d(*player_pos, a.1);
+        let distance_b = bracket_lib::prelude::DistanceAlg::Pythagoras.distance2d(*player_pos, b.1);
+        distance_a.partial_cmp(&distance_b).unwrap()
+    });
+    targets
+}
+
+// Where the cursor starts: the last target if it is still in sight,
+// otherwise the nearest hostile, otherwise the player.
+pub fn first_target(ecs: &World, range: i32, last_target: Option<Entity>) -> Point {
+    let targets = visible_targets(ecs, range);
+    if let Some(last_target) = last_target {
+        if let Some(target) = targets.iter().find(|target| target.0 == last_target) {
+            return target.1;
+        }
+    }
+    match targets.first() {
+        Some(target) => target.1,
+        None => *ecs.fetch::<Point>(),
+    }
+}
+
+pub fn cycle_target(ecs: &World, range: i32, cursor: Point) -> Point {
+    let targets = visible_targets(ecs, range);
+    if targets.is_empty() {
+        return cursor;
+    }
+    let next = match targets.iter().position(|target| target.1 == cursor) {
+        Some(i) => (i + 1) % targets.len(),
+        None => 0,
+    };
+    targets[next].1
+}
+
+pub fn monster_at(ecs: &World, target: Point) -> Option<Entity> {
+    let entities = ecs.entities();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    (&entities, &monsters, &positions)
+        .join()
+        .find(|(_entity, _monster, pos)| pos.x == target.x && pos.y == target.y)
+        .map(|(entity, _monster, _pos)| entity)
+}
+
+pub fn try_target_point(
+    ecs: &World,
+    target: Point,
+    item: Entity,
+    range: i32,
+    action: TargetAction,
+) -> RunState {
     let available_cells = inside_range(ecs, range);
-    for idx in available_cells.iter() {
-        if idx.x == mouse_pos_x + fix_player_pos_left && idx.y == mouse_pos_y + fix_player_pos_top {
-            let mut intent = ecs.write_storage::<WantsToUseItem>();
-            intent
-                .insert(
-                    *ecs.fetch::<Entity>(),
-                    WantsToUseItem {
-                        item,
-                        target: Some(Point::new(
-                            mouse_pos_x + fix_player_pos_left,
-                            mouse_pos_y + fix_player_pos_top,
-                        )),
-                    },
-                )
-                .unwrap();
-            return RunState::PlayerTurn;
+    if available_cells.contains(&target) {
+        match action {
+            TargetAction::Use => {
+                let mut intent = ecs.write_storage::<WantsToUseItem>();
+                intent
+                    .insert(
+                        *ecs.fetch::<Entity>(),
+                        WantsToUseItem {
+                            item,
+                            target: Some(target),
+                        },
+                    )
+                    .unwrap();
+            }
+            TargetAction::Fire | TargetAction::Throw => {
+                let mut intent = ecs.write_storage::<WantsToThrow>();
+                intent
+                    .insert(
+                        *ecs.fetch::<Entity>(),
+                        WantsToThrow {
+                            item,
+                            target,
+                            fired: action == TargetAction::Fire,
+                        },
+                    )
+                    .unwrap();
+            }
         }
+        return RunState::PlayerTurn;
     }
     RunState::AwaitingInput
 }